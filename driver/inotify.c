@@ -73,18 +73,12 @@ inotify_wait4_creation(int inotify_fd, const char *path, const bool *keep_runnin
 }
 
 
-int
-inotify_setup(const char *path, const bool *keep_running, int *watch_d)
+static int
+inotify_watch(int inotify_fd, const char *path, const bool *keep_running)
 {
-    int inotify_fd = inotify_init1(IN_NONBLOCK);
-    if (inotify_fd == -1) {
-        PLOG_F("failed to init inotify");
-        return -1;
-    }
-
     int wd = 0;
     while (*keep_running) {
-        wd = inotify_add_watch(inotify_fd, path, IN_CLOSE_WRITE | IN_DELETE_SELF);
+        wd = inotify_add_watch(inotify_fd, path, IN_CLOSE_WRITE | IN_DELETE_SELF | IN_MOVE_SELF);
         if (wd == -1) {
             if (errno == ENOENT) {
                 if (inotify_wait4_creation(inotify_fd, path, keep_running) == -1) {
@@ -98,19 +92,44 @@ inotify_setup(const char *path, const bool *keep_running, int *watch_d)
         break;
     }
 
+    return wd;
+}
+
+
+int
+inotify_setup(const char *path, const bool *keep_running, int *watch_d)
+{
+    int inotify_fd = inotify_init1(IN_NONBLOCK);
+    if (inotify_fd == -1) {
+        PLOG_F("failed to init inotify");
+        return -1;
+    }
+
+    int wd = inotify_watch(inotify_fd, path, keep_running);
+    if (wd == -1) {
+        return -1;
+    }
+
     *watch_d = wd;
     return inotify_fd;
 }
 
 
 static bool
-inotify_process_event(int wd, const char *path,
-                      const struct inotify_event *in_event, const char **name)
+inotify_process_event(int wd, const char *path, const struct inotify_event *in_event,
+                      const char **name, bool *moved)
 {
     if (in_event->wd != wd) {
         return true;
     }
 
+    // the fuzzer moved the corpus away (e.g. AFL resuming in place), a new one will take its place
+    if ((in_event->mask & IN_MOVE_SELF) == IN_MOVE_SELF) {
+        LOG_I("corpus directory %s moved", path);
+        *moved = true;
+        return true;
+    }
+
     if ((in_event->mask & IN_DELETE_SELF) == IN_DELETE_SELF) {
         LOG_E("corpus directory %s deleted", path);
         return false;
@@ -127,8 +146,8 @@ inotify_process_event(int wd, const char *path,
 
 
 bool
-inotify_maybe_read(int inotify_fd, int wd, const char *path, HashSet *seen,
-                   char **names, size_t *names_len)
+inotify_maybe_read(int inotify_fd, int *wd, const char *path, const bool *keep_running,
+                   HashSet *seen, char **names, size_t *names_len)
 {
     struct inotify_event *in_event_mem = inotify_event_new();
 
@@ -141,11 +160,12 @@ inotify_maybe_read(int inotify_fd, int wd, const char *path, HashSet *seen,
     }
 
     size_t names_i = 0, in_event_i = 0, in_event_offset = 0;
+    bool moved = false;
     while (in_event_offset < ret) {
         const char *ptr = ((char *) in_event_mem) + in_event_offset;
         const struct inotify_event *in_event = (struct inotify_event*) ptr;
         const char *filename = NULL;
-        if (inotify_process_event(wd, path, in_event, &filename) == false) {
+        if (inotify_process_event(*wd, path, in_event, &filename, &moved) == false) {
             free(in_event_mem);
             return false;
         }
@@ -180,6 +200,16 @@ inotify_maybe_read(int inotify_fd, int wd, const char *path, HashSet *seen,
 
     LOG_D("got %zu inotify events on %s", in_event_i, path);
 
+    if (moved) {
+        inotify_rm_watch(inotify_fd, *wd);
+        *wd = inotify_watch(inotify_fd, path, keep_running);
+        if (*wd == -1) {
+            free(in_event_mem);
+            return false;
+        }
+        LOG_I("watching new corpus directory %s", path);
+    }
+
     *names_len = names_i;
     free(in_event_mem);
     return true;
@@ -11,7 +11,7 @@
 
 
 int inotify_setup(const char *path, const bool *keep_running, int *watch_d);
-bool inotify_maybe_read(int inotify_fd, int wd, const char *path,
+bool inotify_maybe_read(int inotify_fd, int *wd, const char *path, const bool *keep_running,
                         HashSet *seen, char **names, size_t *names_len);
 
 #endif
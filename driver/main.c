@@ -15,9 +15,10 @@
 #include <signal.h>
 #include <sys/fcntl.h>
 #include <sys/wait.h>
+#include <sys/stat.h>
 #include <collectc/hashtable.h>
 #include <collectc/hashset.h>
-#include <libgen.h>
+#include <dirent.h>
 #include <time.h>
 
 
@@ -40,15 +41,23 @@ typedef struct driver {
     void *interesting_push;
     void *use_sub;
     void *metric_rep;
+    void *registration_req;
     const char *data_path;
     const char *inject_path;
+    const char *work_path;
     size_t injected_n;
+    size_t inline_limit;
     HashTable *coverage_info;
     bool single_mode;
+    bool resume;
     struct timespec start_time;
     ssize_t interesting_log_fd;
     ssize_t coverage_log_fd;
     HashSet *interesting_seen;
+    const char *readiness_path;
+    off_t readiness_offset;
+    size_t readiness_lines;
+    struct timespec readiness_checked;
 } driver_t;
 
 
@@ -58,7 +67,22 @@ typedef struct branch {
 } branch_t;
 
 
-typedef float (*metric_fn_t)(driver_t *, branch_t *, size_t);
+// what a driver knows of a branch, the times it was hit over all inputs and the hit count
+// buckets (as AFL defines them) inputs hit it in
+typedef struct branch_info {
+    uint64_t hits;
+    uint8_t buckets;
+} branch_info_t;
+
+
+// metrics get the hit counts of the branches of an input and its size
+typedef float (*metric_fn_t)(driver_t *, HashTable *, size_t);
+
+
+typedef struct metric {
+    const char *name;
+    metric_fn_t fn;
+} metric_t;
 
 
 #define BUF_SZ              (1024 * 1024)
@@ -67,18 +91,27 @@ typedef float (*metric_fn_t)(driver_t *, branch_t *, size_t);
 #define HASH_KEY_SZ         64
 #define COV_FMT             "id:%05zu.%zu.coverage"
 #define INPUT_FMT           "id:%05zu.input"
-#define METRIC_FN           &metric_diff
+#define MAX_METRICS         8
 #define SUB_TOPIC           "A"
-#define MAX_FUZZERS         16
-#define MAX_FUZZER_ID       16
-#define USE_FUZZ_ID_SEP     "_"
-#define WORK_PATH           "./work"        // TODO: make it a cmd line option?
+#define PROTOCOL_VERSION    "uberfuzz/6"
+#define RECEIVED_DIR        "received"
+#define WORK_PATH           "./work"
+#define FUZZER_GRACE_SECS   5
+#define MASTER_HOST         "localhost"
+#define REGISTRATION_TIMEOUT_MS 5000
+#define READINESS_CHECK_US  (500 * 1000)
 
 #if !defined(LOG_LEVEL)
 #define LOG_LEVEL           INFO
 #endif
 
 
+typedef struct frame {
+    char *data;
+    size_t size;
+} frame_t;
+
+
 bool keep_running = true;
 
 
@@ -99,13 +132,14 @@ start_fuzzer(driver_t *driver)
         PLOG_F("failed to fork");
         return -1;
     } else if (pid == 0) {
-        // child, run fuzzer
+        // child, run fuzzer (appending to the logs of the previous run when resuming)
+        const int log_flags = O_WRONLY | O_CREAT | (driver->resume ? O_APPEND : O_TRUNC);
         const char *filename = driver->fuzzer_log_filename == NULL ?
                                 "/dev/null" : driver->fuzzer_log_filename;
 
         int fd = driver->fuzzer_log_filename == NULL ?
                 open(filename, O_WRONLY) :
-                open(filename, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+                open(filename, log_flags, 0644);
 
         if (fd == -1) {
             PLOG_F("failed to open %s", filename);
@@ -117,7 +151,7 @@ start_fuzzer(driver_t *driver)
 
         int err_fd = driver->fuzzer_log_err_filename == NULL ?
                     open(err_filename, O_WRONLY) :
-                    open(err_filename, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+                    open(err_filename, log_flags, 0644);
 
         if (err_fd == -1) {
             PLOG_F("failed to open %s", err_filename);
@@ -127,16 +161,56 @@ start_fuzzer(driver_t *driver)
         dup2(fd, STDOUT_FILENO);
         dup2(err_fd, STDERR_FILENO);
 
+        // own process group, so that the whole fuzzer tree can be signaled at once
+        setpgid(0, 0);
+
         execv(driver->fuzzer[0], driver->fuzzer);
         PLOG_F("failed to execv fuzzer");
         abort();
     } else {
-        // parent, child_pid = pid
+        // parent, child_pid = pid. The group is set from here too, as the fuzzer may be signaled
+        // before the child gets to it (EACCES once it exec'd, ESRCH if it is gone already)
+        if (setpgid(pid, pid) == -1 && errno != EACCES && errno != ESRCH)
+            PLOG_W("failed to set the process group of the fuzzer (pid=%d)", pid);
         return pid;
     }
 }
 
 
+// terminate the fuzzer process group, killing it if still alive after FUZZER_GRACE_SECS
+static bool
+stop_fuzzer(driver_t *driver)
+{
+    if (!driver->fuzzer_running || driver->fuzzer_pid <= 0)
+        return true;
+
+    if (kill(-driver->fuzzer_pid, SIGTERM) == -1) {
+        PLOG_W("failed to terminate fuzzer (pid=%d)", driver->fuzzer_pid);
+    } else {
+        struct timespec stop_time;
+        clock_gettime(CLOCK_MONOTONIC_RAW, &stop_time);
+        while (get_delta_micro(&stop_time) < FUZZER_GRACE_SECS * 1000000) {
+            pid_t wait_res = waitpid(driver->fuzzer_pid, NULL, WNOHANG);
+            if (wait_res == driver->fuzzer_pid || (wait_res == -1 && errno == ECHILD)) {
+                driver->fuzzer_running = false;
+                LOG_I("fuzzer terminated (pid=%d)", driver->fuzzer_pid);
+                return true;
+            }
+            usleep(100000);
+        }
+        LOG_W("fuzzer still running after %ds", FUZZER_GRACE_SECS);
+    }
+
+    if (kill(-driver->fuzzer_pid, SIGKILL) == -1) {
+        PLOG_F("failed to kill fuzzer (pid=%d)", driver->fuzzer_pid);
+        return false;
+    }
+    waitpid(driver->fuzzer_pid, NULL, 0);
+    driver->fuzzer_running = false;
+    return true;
+}
+
+
 static inline char *
 coverage_info_key(branch_t *branch)
 {
@@ -148,34 +222,233 @@ coverage_info_key(branch_t *branch)
 }
 
 
-static ssize_t
-add_coverage_info(driver_t *driver, branch_t *coverage_info, size_t count)
+// the bucket of a hit count: 1, 2, 3, 4-7, 8-15, 16-31, 32-127 and 128 or more
+static inline uint8_t
+hits_bucket(uint64_t hits)
 {
-    ssize_t added_unique_branches = 0;
+    if (hits <= 3)
+        return 1 << (hits - 1);
+    if (hits < 32)
+        return hits < 8 ? 1 << 3 : hits < 16 ? 1 << 4 : 1 << 5;
+    return hits < 128 ? 1 << 6 : 1 << 7;
+}
+
+
+static void
+free_hashtable(HashTable *table)
+{
+    HashTableIter hti;
+    hashtable_iter_init(&hti, table);
+    TableEntry *entry = NULL;
+    while (hashtable_iter_next(&hti, &entry) != CC_ITER_END) {
+        free(entry->key);
+        free(entry->value);
+    }
+    hashtable_destroy(table);
+}
+
+
+// hit counts of the branches of an input, by branch key
+static HashTable *
+count_hits(branch_t *coverage_info, size_t count)
+{
+    HashTable *hits_table;
+    if (hashtable_new(&hits_table) != CC_OK)
+        return NULL;
+
     for (size_t i = 0; i < count; i++) {
-        branch_t branch = coverage_info[i];
-        char *key = coverage_info_key(&branch);
+        char *key = coverage_info_key(&coverage_info[i]);
 
-        uint64_t *value = NULL;
-        if (hashtable_get(driver->coverage_info, key, (void **) &value) == CC_OK) {
-            (*value)++;
+        uint64_t *hits = NULL;
+        if (hashtable_get(hits_table, key, (void **) &hits) == CC_OK) {
+            (*hits)++;
             free(key);
         } else {
-            value = malloc(sizeof(uint64_t));
-            assert(value != NULL);
-            *value = 1;
-            added_unique_branches++;
-            if (hashtable_add(driver->coverage_info, key, value) != CC_OK) {
+            hits = malloc(sizeof(uint64_t));
+            assert(hits != NULL);
+            *hits = 1;
+            if (hashtable_add(hits_table, key, hits) != CC_OK) {
+                LOG_F("failed to count hits of branch [%s]", key);
+                free(key);
+                free(hits);
+                free_hashtable(hits_table);
+                return NULL;
+            }
+        }
+    }
+
+    return hits_table;
+}
+
+
+static ssize_t
+add_coverage_info(driver_t *driver, branch_t *coverage_info, size_t count)
+{
+    HashTable *hits_table = count_hits(coverage_info, count);
+    if (hits_table == NULL)
+        return -1;
+
+    ssize_t added_unique_branches = 0;
+    HashTableIter hti;
+    hashtable_iter_init(&hti, hits_table);
+    TableEntry *entry = NULL;
+    while (hashtable_iter_next(&hti, &entry) != CC_ITER_END) {
+        uint64_t hits = *((uint64_t *) entry->value);
+
+        branch_info_t *info = NULL;
+        if (hashtable_get(driver->coverage_info, entry->key, (void **) &info) != CC_OK) {
+            info = calloc(1, sizeof(branch_info_t));
+            assert(info != NULL);
+            char *key = strdup(entry->key);
+            assert(key != NULL);
+            if (hashtable_add(driver->coverage_info, key, info) != CC_OK) {
                 LOG_F("failed to add branch [%s]", key);
+                free(key);
+                free(info);
+                free_hashtable(hits_table);
                 return -1;
             }
+            added_unique_branches++;
         }
+        info->hits += hits;
+        info->buckets |= hits_bucket(hits);
     }
 
+    free_hashtable(hits_table);
     return added_unique_branches;
 }
 
 
+// send a multipart message, frames are strings unless their sizes are given
+static bool
+send_frames(void *socket, const void **frames, const size_t *sizes, size_t frames_n)
+{
+    for (size_t i = 0; i < frames_n; i++) {
+        int flags = i + 1 < frames_n ? ZMQ_SNDMORE : 0;
+        size_t size = sizes == NULL ? strlen(frames[i]) : sizes[i];
+        if (zmq_send(socket, frames[i], size, flags) == -1)
+            return false;
+    }
+
+    return true;
+}
+
+
+static void
+free_frames(frame_t *frames, size_t frames_n)
+{
+    for (size_t i = 0; i < frames_n; i++)
+        free(frames[i].data);
+    free(frames);
+}
+
+
+// receive a multipart message into NUL terminated frames, as many as it has (use messages have a
+// frame per receiver), to be freed with free_frames. Returns the number of frames or -1.
+static ssize_t
+recv_frames(void *socket, frame_t **frames, int flags)
+{
+    size_t frames_n = 0, frames_cap = 0;
+    *frames = NULL;
+    int more = 1;
+    while (more) {
+        zmq_msg_t msg;
+        zmq_msg_init(&msg);
+        if (zmq_msg_recv(&msg, socket, frames_n == 0 ? flags : 0) == -1) {
+            int recv_errno = errno;
+            zmq_msg_close(&msg);
+            free_frames(*frames, frames_n);
+            errno = recv_errno;
+            return -1;
+        }
+        more = zmq_msg_more(&msg);
+
+        if (frames_n == frames_cap) {
+            frames_cap = frames_cap == 0 ? 8 : frames_cap * 2;
+            *frames = realloc(*frames, frames_cap * sizeof(frame_t));
+            assert(*frames != NULL);
+        }
+        size_t size = zmq_msg_size(&msg);
+        (*frames)[frames_n].data = malloc(size + 1);
+        assert((*frames)[frames_n].data != NULL);
+        memcpy((*frames)[frames_n].data, zmq_msg_data(&msg), size);
+        (*frames)[frames_n].data[size] = '\0';
+        (*frames)[frames_n].size = size;
+        frames_n++;
+        zmq_msg_close(&msg);
+    }
+
+    return frames_n;
+}
+
+
+// check the protocol version (found at `offset`) and the kind of a message
+static bool
+check_message(const frame_t *frames, size_t frames_n, size_t offset, const char *kind,
+              size_t fields_n)
+{
+    if (frames_n <= offset || strcmp(frames[offset].data, PROTOCOL_VERSION) != 0) {
+        LOG_F("protocol version mismatch: got '%s', expected '%s'",
+            frames_n > offset ? frames[offset].data : "", PROTOCOL_VERSION);
+        return false;
+    }
+
+    if (frames_n < offset + 2 + fields_n || strcmp(frames[offset + 1].data, kind) != 0) {
+        LOG_F("expected %s message with %zu fields, got %s with %zu", kind, fields_n,
+            frames_n > offset + 1 ? frames[offset + 1].data : "nothing",
+            frames_n > offset + 2 ? frames_n - offset - 2 : 0);
+        return false;
+    }
+
+    return true;
+}
+
+
+// FNV-1a
+static uint64_t
+hash_data(const uint8_t *data, size_t size)
+{
+    uint64_t hash = 0xcbf29ce484222325;
+    for (size_t i = 0; i < size; i++) {
+        hash ^= data[i];
+        hash *= 0x100000001b3;
+    }
+    return hash;
+}
+
+
+// write contents received from the master to a file named after their hash (so that the same
+// contents are written once), `path` receives its name
+static bool
+store_received(driver_t *driver, const frame_t *frame, const char *extension, char *path)
+{
+    if (frame->size > driver->inline_limit) {
+        LOG_F("received %zu bytes, more than the inline limit of %zu", frame->size,
+            driver->inline_limit);
+        return false;
+    }
+
+    snprintf(path, PATH_MAX - 1, "%s/" RECEIVED_DIR "/%016" PRIx64 ".%s", driver->data_path,
+        hash_data((uint8_t *) frame->data, frame->size), extension);
+    if (access(path, F_OK) == 0)
+        return true;
+
+    FILE *file = fopen(path, "wb");
+    if (file == NULL) {
+        PLOG_F("failed to open %s", path);
+        return false;
+    }
+    if (fwrite(frame->data, sizeof(uint8_t), frame->size, file) != frame->size) {
+        LOG_E("items written to %s do not match", path);
+        fclose(file);
+        return false;
+    }
+    fclose(file);
+
+    return true;
+}
+
+
 static bool
 process_interesting_input(driver_t *driver, uint8_t *buf, size_t size)
 {
@@ -252,7 +525,6 @@ process_interesting_input(driver_t *driver, uint8_t *buf, size_t size)
     }
     fflush(coverage_file);
     fclose(coverage_file);
-    free(branches);
 
     // store input to file
     char input_filename[PATH_MAX];
@@ -288,7 +560,7 @@ process_interesting_input(driver_t *driver, uint8_t *buf, size_t size)
         TableEntry *entry = NULL;
         size_t branch_hits = 0;
         while (hashtable_iter_next(&hti, &entry) != CC_ITER_END)
-            branch_hits += *((uint64_t *) entry->value);
+            branch_hits += ((branch_info_t *) entry->value)->hits;
         snprintf(line, PATH_MAX - 1, "%" PRIu64 " %zu %zu %zu\n",
             delta_time, hashtable_size(driver->coverage_info),
             new_branches, branch_hits);
@@ -297,16 +569,28 @@ process_interesting_input(driver_t *driver, uint8_t *buf, size_t size)
             return false;
         }
     } else {
-        // send zmq message
-        char message[PATH_MAX * 2];
-        snprintf(message, (PATH_MAX * 2) - 1, "%s %s %s",
-                 driver->fuzzer_id, input_filename, coverage_filename);
-        if (zmq_send(driver->interesting_push, message, strlen(message), 0) == -1) {
+        // send zmq message, with the contents of the input and coverage files in inline mode
+        const void *frames[] = {
+            PROTOCOL_VERSION, "interesting", driver->fuzzer_id, input_filename, coverage_filename,
+            buf, branches
+        };
+        size_t sizes[] = {
+            strlen(frames[0]), strlen(frames[1]), strlen(frames[2]), strlen(frames[3]),
+            strlen(frames[4]), size, branches_i * sizeof(branch_t)
+        };
+        size_t frames_n = driver->inline_limit > 0 ? 7 : 5;
+
+        if (frames_n == 7 && (sizes[5] > driver->inline_limit || sizes[6] > driver->inline_limit)) {
+            LOG_W("not reporting input %zu, larger than the inline limit of %zu",
+                driver->input_n, driver->inline_limit);
+        } else if (!send_frames(driver->interesting_push, frames, sizes, frames_n)) {
             PLOG_F("failed pushing on the interesting queue");
+            free(branches);
             return false;
         }
     }
 
+    free(branches);
     return true;
 }
 
@@ -316,20 +600,23 @@ load_coverage_info(const char *cov_filename, branch_t **cov_info)
 {
     assert(cov_info != NULL);
 
-    // parse cov_count from filename
-    char *cov_filename_dup = strdup(cov_filename);
-    char *cov_basename = basename(cov_filename_dup);
-    size_t cov_count = 0, input_n = 0;
-    sscanf(cov_basename, COV_FMT, &input_n, &cov_count);
-    free(cov_filename_dup);
-
     FILE *cov_file = fopen(cov_filename, "rb");
     if (cov_file == NULL) {
         PLOG_F("failed to open %s", cov_filename);
         return -1;
     }
 
-    *cov_info = malloc(sizeof(branch_t) * cov_count);
+    // the count is taken from the file size, as coverage received from the master (in inline
+    // mode) is not named after it
+    struct stat cov_stat;
+    if (fstat(fileno(cov_file), &cov_stat) == -1) {
+        PLOG_F("failed to stat %s", cov_filename);
+        fclose(cov_file);
+        return -1;
+    }
+    size_t cov_count = cov_stat.st_size / sizeof(branch_t);
+
+    *cov_info = malloc(sizeof(branch_t) * cov_count + 1);
     assert(*cov_info != NULL);
     size_t r = fread(*cov_info, sizeof(branch_t), cov_count, cov_file);
 
@@ -342,53 +629,122 @@ load_coverage_info(const char *cov_filename, branch_t **cov_info)
 }
 
 
+// branches of the input the fuzzer does not know of
 static float
-metric_diff(driver_t *driver, branch_t *cov_info, size_t cov_count)
+metric_new_branches(driver_t *driver, HashTable *hits_table, size_t input_size)
 {
-    HashSet *seen_branches;
-    if (hashset_new(&seen_branches) != CC_OK)
-        return 0;
+    (void) input_size;
 
     float score = 0;
-    for (size_t i = 0; i < cov_count; i++) {
-        branch_t branch = cov_info[i];
-        char *key = coverage_info_key(&branch);
-        if (!hashtable_contains_key(driver->coverage_info, key)) {
-            if (!hashset_contains(seen_branches, key)) {
-                assert(hashset_add(seen_branches, key) == CC_OK);
-                score++;
-                continue;
-            }
-        }
-        free(key);
+    HashTableIter hti;
+    hashtable_iter_init(&hti, hits_table);
+    TableEntry *entry = NULL;
+    while (hashtable_iter_next(&hti, &entry) != CC_ITER_END) {
+        if (!hashtable_contains_key(driver->coverage_info, entry->key))
+            score++;
     }
 
-    HashSetIter hsi;
-    hashset_iter_init(&hsi, seen_branches);
-    void *entry;
-    while (hashset_iter_next(&hsi, &entry) != CC_ITER_END)
-        free(entry);
-    hashset_destroy(seen_branches);
+    return score;
+}
+
+
+// the share of the branches of the input the fuzzer does not know of
+static float
+metric_new_fraction(driver_t *driver, HashTable *hits_table, size_t input_size)
+{
+    size_t branches_n = hashtable_size(hits_table);
+    if (branches_n == 0)
+        return 0;
+
+    return metric_new_branches(driver, hits_table, input_size) / branches_n;
+}
+
+
+// branches the input hits a number of times (in AFL buckets) the fuzzer has not seen yet
+static float
+metric_hit_buckets(driver_t *driver, HashTable *hits_table, size_t input_size)
+{
+    (void) input_size;
+
+    float score = 0;
+    HashTableIter hti;
+    hashtable_iter_init(&hti, hits_table);
+    TableEntry *entry = NULL;
+    while (hashtable_iter_next(&hti, &entry) != CC_ITER_END) {
+        uint8_t bucket = hits_bucket(*((uint64_t *) entry->value));
+        branch_info_t *info = NULL;
+        if (hashtable_get(driver->coverage_info, entry->key, (void **) &info) != CC_OK ||
+            (info->buckets & bucket) == 0)
+            score++;
+    }
 
     return score;
 }
 
 
-static bool
-compute_metric(driver_t *driver, const char *cov_filename, metric_fn_t f, float *score)
+// new branches per KiB of input (started), favouring small inputs
+static float
+metric_size_penalty(driver_t *driver, HashTable *hits_table, size_t input_size)
+{
+    return metric_new_branches(driver, hits_table, input_size) / (1 + input_size / 1024);
+}
+
+
+// the metrics drivers compute, the ones needing the coverage of the whole campaign are computed
+// by the master
+static const metric_t METRICS[] = {
+    { "new-branches", &metric_new_branches },
+    { "new-fraction", &metric_new_fraction },
+    { "hit-buckets", &metric_hit_buckets },
+    { "size-penalty", &metric_size_penalty }
+};
+
+
+static metric_fn_t
+find_metric(const char *name)
 {
-    assert(score != NULL);
+    for (size_t i = 0; i < sizeof(METRICS) / sizeof(METRICS[0]); i++) {
+        if (strcmp(METRICS[i].name, name) == 0)
+            return METRICS[i].fn;
+    }
+    return NULL;
+}
+
 
+// compute the metrics with the given (comma separated) names of the coverage in a file
+static bool
+compute_metrics(driver_t *driver, const char *cov_filename, const char *names,
+                size_t input_size, float *scores, size_t *scores_n)
+{
     branch_t *cov_info = NULL;
     ssize_t cov_count = load_coverage_info(cov_filename, &cov_info);
     if (cov_count < 0) {
         return false;
     }
 
-    *score = f(driver, cov_info, cov_count);
+    HashTable *hits_table = count_hits(cov_info, cov_count);
     free(cov_info);
+    if (hits_table == NULL)
+        return false;
 
-    return true;
+    char names_copy[RECV_BUF_SZ];
+    snprintf(names_copy, RECV_BUF_SZ, "%s", names);
+    *scores_n = 0;
+    bool computed = true;
+    char *saveptr = NULL;
+    for (char *name = strtok_r(names_copy, ",", &saveptr); name != NULL;
+         name = strtok_r(NULL, ",", &saveptr)) {
+        metric_fn_t f = find_metric(name);
+        if (f == NULL || *scores_n == MAX_METRICS) {
+            LOG_F("unknown metric %s (or more than %d)", name, MAX_METRICS);
+            computed = false;
+            break;
+        }
+        scores[(*scores_n)++] = f(driver, hits_table, input_size);
+    }
+
+    free_hashtable(hits_table);
+    return computed;
 }
 
 
@@ -480,6 +836,297 @@ use_input(driver_t *driver, const char *input_path, const char *coverage_path)
 }
 
 
+// mark the files in a directory as seen, `next_n` is set past the highest id found (if not NULL)
+static bool
+mark_seen_dir(driver_t *driver, const char *dir_path, size_t *next_n)
+{
+    DIR *dir = opendir(dir_path);
+    if (dir == NULL) {
+        // the fuzzer might not have created it yet
+        if (errno == ENOENT)
+            return true;
+        PLOG_F("failed to open %s", dir_path);
+        return false;
+    }
+
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (entry->d_name[0] == '.')
+            continue;
+
+        size_t n = 0;
+        if (next_n != NULL && sscanf(entry->d_name, "id:%zu", &n) == 1 && n >= *next_n)
+            *next_n = n + 1;
+
+        char *file_path = malloc(PATH_MAX * sizeof(char));
+        assert(file_path != NULL);
+        snprintf(file_path, PATH_MAX - 1, "%s/%s", dir_path, entry->d_name);
+        file_path = realloc(file_path, strlen(file_path) + 1);
+
+        if (hashset_contains(driver->interesting_seen, file_path)) {
+            free(file_path);
+        } else if (hashset_add(driver->interesting_seen, file_path) != CC_OK) {
+            LOG_W("failed to add %s to seen inputs hashset", file_path);
+            free(file_path);
+        }
+    }
+
+    closedir(dir);
+    return true;
+}
+
+
+// restore the state left by a previous run: the coverage of the processed inputs, the numbering
+// of processed and injected inputs and the corpus files already seen
+static bool
+resume_driver(driver_t *driver)
+{
+    DIR *dir = opendir(driver->data_path);
+    if (dir == NULL) {
+        PLOG_F("failed to open %s", driver->data_path);
+        return false;
+    }
+
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        size_t input_n = 0, cov_count = 0;
+        if (sscanf(entry->d_name, "id:%zu", &input_n) != 1)
+            continue;
+        if (input_n >= driver->input_n)
+            driver->input_n = input_n + 1;
+
+        if (sscanf(entry->d_name, COV_FMT, &input_n, &cov_count) != 2 || cov_count == 0)
+            continue;
+
+        char coverage_filename[PATH_MAX];
+        snprintf(coverage_filename, PATH_MAX - 1, "%s/%s", driver->data_path, entry->d_name);
+        branch_t *cov_info = NULL;
+        ssize_t count = load_coverage_info(coverage_filename, &cov_info);
+        bool coverage_added = count >= 0 && add_coverage_info(driver, cov_info, count) != -1;
+        free(cov_info);
+        if (!coverage_added) {
+            LOG_F("failed to restore coverage info from %s", coverage_filename);
+            closedir(dir);
+            return false;
+        }
+    }
+    closedir(dir);
+
+    if (driver->inject_path && !mark_seen_dir(driver, driver->inject_path, &driver->injected_n))
+        return false;
+    if (!mark_seen_dir(driver, driver->fuzzer_corpus_path, NULL))
+        return false;
+
+    LOG_I("resuming from input %zu (%zu injected, %zu branches known)",
+        driver->input_n, driver->injected_n, hashtable_size(driver->coverage_info));
+    return true;
+}
+
+
+// compute the metric of the coverage in a request (a path or the coverage itself in inline mode)
+// let the master know why there is no metric, returns false if even that fails
+static bool
+reply_metric_error(driver_t *driver, const char *reason)
+{
+    const void *error_frames[] = { PROTOCOL_VERSION, "error", reason };
+    if (!send_frames(driver->metric_rep, error_frames, NULL, 3)) {
+        PLOG_F("failed to send metric error reply");
+        return false;
+    }
+    return true;
+}
+
+
+static bool
+reply_metric(driver_t *driver, const frame_t *frames, size_t frames_n)
+{
+    if (!check_message(frames, frames_n, 0, "metric", 3)) {
+        reply_metric_error(driver, "unexpected metric request (protocol mismatch?)");
+        return false;
+    }
+
+    // metric names, input size, coverage path and the coverage itself in inline mode
+    char coverage_path[PATH_MAX];
+    if (frames_n > 5) {
+        // the request still needs a reply, the driver can go on with the next ones
+        if (!store_received(driver, &frames[5], "coverage", coverage_path))
+            return reply_metric_error(driver, "failed to store the coverage");
+    } else {
+        snprintf(coverage_path, PATH_MAX - 1, "%s", frames[4].data);
+    }
+    size_t input_size = strtoull(frames[3].data, NULL, 10);
+
+    LOG_I("metric req %s (%s)", frames[4].data, frames[2].data);
+    float scores[MAX_METRICS];
+    size_t scores_n = 0;
+    if (!compute_metrics(driver, coverage_path, frames[2].data, input_size, scores, &scores_n)) {
+        LOG_E("failed to compute metrics");
+        return reply_metric_error(driver, "failed to compute metrics (unknown metric?)");
+    }
+
+    char scores_str[MAX_METRICS][RECV_BUF_SZ];
+    const void *rep_frames[2 + MAX_METRICS] = { PROTOCOL_VERSION, "metric" };
+    for (size_t i = 0; i < scores_n; i++) {
+        snprintf(scores_str[i], RECV_BUF_SZ, "%f", scores[i]);
+        rep_frames[2 + i] = scores_str[i];
+        LOG_I("computed metric %f", scores[i]);
+    }
+    if (!send_frames(driver->metric_rep, rep_frames, NULL, 2 + scores_n)) {
+        PLOG_F("failed to send metric reply");
+        return false;
+    }
+
+    return true;
+}
+
+
+// use an input published by the master if this driver is among its receivers
+static bool
+maybe_use_input(driver_t *driver, const frame_t *frames, size_t frames_n)
+{
+    // topic, version, kind, input path, coverage path, the input and coverage contents (in
+    // inline mode) and the receiving fuzzer ids
+    bool with_data = frames_n > 2 && strcmp(frames[2].data, "use-data") == 0;
+    if (!check_message(frames, frames_n, 1, with_data ? "use-data" : "use", with_data ? 5 : 3))
+        return false;
+
+    size_t receivers_start = with_data ? 7 : 5;
+    bool use_it = false;
+    for (size_t i = receivers_start; i < frames_n; i++) {
+        if (strcmp(frames[i].data, driver->fuzzer_id) == 0) {
+            use_it = true;
+            break;
+        }
+    }
+
+    if (!use_it)
+        return true;
+
+    char input_path[PATH_MAX], coverage_path[PATH_MAX];
+    if (with_data) {
+        if (!store_received(driver, &frames[5], "input", input_path) ||
+            !store_received(driver, &frames[6], "coverage", coverage_path))
+            return false;
+    } else {
+        snprintf(input_path, PATH_MAX - 1, "%s", frames[3].data);
+        snprintf(coverage_path, PATH_MAX - 1, "%s", frames[4].data);
+    }
+
+    LOG_I("using %s", frames[3].data);
+    if (!use_input(driver, input_path, coverage_path)) {
+        LOG_F("failed to use input");
+        return false;
+    }
+
+    return true;
+}
+
+
+// send a message on the registration socket and wait for the master to accept it
+static bool
+registration_request(driver_t *driver, const void **frames, size_t frames_n)
+{
+    const char *kind = frames[1];
+    if (!send_frames(driver->registration_req, frames, NULL, frames_n)) {
+        PLOG_F("failed to send %s", kind);
+        return false;
+    }
+
+    frame_t *reply;
+    ssize_t reply_n = recv_frames(driver->registration_req, &reply, 0);
+    if (reply_n == -1) {
+        PLOG_F("no reply to %s from the master", kind);
+        return false;
+    }
+
+    bool accepted = false;
+    if (reply_n == 3 && strcmp(reply[0].data, PROTOCOL_VERSION) == 0 &&
+        strcmp(reply[1].data, "error") == 0) {
+        LOG_F("master refused %s: %s", kind, reply[2].data);
+    } else {
+        accepted = check_message(reply, reply_n, 0, "registered", 0);
+    }
+
+    free_frames(reply, reply_n);
+    return accepted;
+}
+
+
+// the fuzzer appends a line to its readiness file at the end of every generation: count the
+// complete lines (a partial one is read again next time) and tell the master when a generation
+// ends. The file is opened by path every time, as the fuzzer may replace it.
+static bool
+maybe_send_ready(driver_t *driver)
+{
+    if (get_delta_micro(&driver->readiness_checked) < READINESS_CHECK_US)
+        return true;
+    clock_gettime(CLOCK_MONOTONIC_RAW, &driver->readiness_checked);
+
+    int fd = open(driver->readiness_path, O_RDONLY);
+    if (fd == -1) {
+        if (errno == ENOENT)
+            return true;
+        PLOG_E("failed to open %s", driver->readiness_path);
+        return false;
+    }
+
+    struct stat st;
+    if (fstat(fd, &st) == -1) {
+        PLOG_E("failed to stat %s", driver->readiness_path);
+        close(fd);
+        return false;
+    }
+
+    // a file shorter than what was read already was replaced, start over
+    if (st.st_size < driver->readiness_offset) {
+        driver->readiness_offset = 0;
+        driver->readiness_lines = 0;
+    }
+
+    if (lseek(fd, driver->readiness_offset, SEEK_SET) == -1) {
+        PLOG_E("failed to seek in %s", driver->readiness_path);
+        close(fd);
+        return false;
+    }
+
+    char buf[RECV_BUF_SZ];
+    ssize_t read_size;
+    off_t position = driver->readiness_offset;
+    size_t lines = 0;
+    while ((read_size = read(fd, buf, sizeof(buf))) > 0) {
+        for (ssize_t i = 0; i < read_size; i++) {
+            if (buf[i] == '\n') {
+                lines++;
+                driver->readiness_offset = position + i + 1;
+            }
+        }
+        position += read_size;
+    }
+    close(fd);
+
+    if (read_size == -1) {
+        PLOG_E("failed to read %s", driver->readiness_path);
+        return false;
+    }
+
+    // as for the inotify fallback of the master, the first line does not end a generation
+    driver->readiness_lines += lines;
+    if (lines == 0 || driver->readiness_lines < 2)
+        return true;
+
+    char generation[32];
+    sprintf(generation, "%zu", driver->readiness_lines - 1);
+    const void *ready_frames[] = { PROTOCOL_VERSION, "ready", driver->fuzzer_id, generation };
+    if (!send_frames(driver->interesting_push, ready_frames, NULL, 4)) {
+        PLOG_E("failed to send ready");
+        return false;
+    }
+    LOG_D("generation %s ended", generation);
+
+    return true;
+}
+
+
 static int
 driver_loop(driver_t *driver)
 {
@@ -521,19 +1168,14 @@ driver_loop(driver_t *driver)
             }
         }
 
-        if (!keep_running) {
-            if (kill(driver->fuzzer_pid, SIGKILL) == -1) {
-                PLOG_F("failed to kill fuzzer (pid=%d)", driver->fuzzer_pid);
-                ret = EXIT_FAILURE;
-            }
+        if (!keep_running)
             break;
-        }
 
         // 1. look if fuzzer has a new input -> process it
         char *new_names[IN_NAMES_MAX];
         size_t new_names_n = 0;
         bool inotify_res = inotify_maybe_read(
-            inotify_fd, watch_d, driver->fuzzer_corpus_path,
+            inotify_fd, &watch_d, driver->fuzzer_corpus_path, &keep_running,
             driver->interesting_seen, new_names, &new_names_n);
         if (!inotify_res) {
             ret = EXIT_FAILURE;
@@ -578,83 +1220,58 @@ driver_loop(driver_t *driver)
         usleep(100);
 
         if (!driver->single_mode) {
-            uint8_t zmq_recv_buf[RECV_BUF_SZ];
-            memset(zmq_recv_buf, 0, RECV_BUF_SZ);
+            frame_t *frames;
 
             // 2. look for metric requests -> reply to request
-            int size = zmq_recv(driver->metric_rep, zmq_recv_buf, RECV_BUF_SZ-1, ZMQ_DONTWAIT);
-            if (size == -1) {
+            ssize_t frames_n = recv_frames(driver->metric_rep, &frames, ZMQ_DONTWAIT);
+            if (frames_n == -1) {
                 if (errno != EAGAIN && errno != EFSM) {
                     PLOG_F("failed receiving on the metric queue");
                     ret = EXIT_FAILURE;
                     break;
                 }
             } else {
-                zmq_recv_buf[size] = '\0';
-                LOG_I("metric req %s", zmq_recv_buf);
-                float metric = 0;
-                if (!compute_metric(driver, (const char *) zmq_recv_buf, METRIC_FN, &metric)) {
-                    LOG_F("failed to compute metric");
-                    ret = EXIT_FAILURE;
-                    break;
-                }
-                LOG_I("computed metric %f", metric);
-
-                char zmq_send_buf[RECV_BUF_SZ];
-                snprintf(zmq_send_buf, RECV_BUF_SZ, "%f", metric);
-                int r = zmq_send(driver->metric_rep, zmq_send_buf, strlen(zmq_send_buf), 0);
-                if (r == -1) {
-                    PLOG_F("failed to send metric reply");
+                bool replied = reply_metric(driver, frames, frames_n);
+                free_frames(frames, frames_n);
+                if (!replied) {
                     ret = EXIT_FAILURE;
                     break;
                 }
             }
 
-            memset(zmq_recv_buf, 0, RECV_BUF_SZ);
             usleep(100);
 
 
             // 3. look for new inputs to use/fuzz -> inject into fuzzer
-            size = zmq_recv(driver->use_sub, zmq_recv_buf, RECV_BUF_SZ-1, ZMQ_DONTWAIT);
-            if (size == -1) {
+            frames_n = recv_frames(driver->use_sub, &frames, ZMQ_DONTWAIT);
+            if (frames_n == -1) {
                 if (errno != EAGAIN) {
                     PLOG_F("failed receiving on the use queue");
                     ret = EXIT_FAILURE;
                     break;
                 }
             } else {
-                zmq_recv_buf[size] = '\0';
-                // parse 'use' message
-                char fuzzer_ids_str[MAX_FUZZERS * MAX_FUZZER_ID];
-                char input_path[PATH_MAX], coverage_path[PATH_MAX];
-                sscanf((char *) zmq_recv_buf, SUB_TOPIC " %s %s %s",
-                    fuzzer_ids_str, input_path, coverage_path);
-
-                // parse fuzzer ids
-                char *fuzzer_id = strtok(fuzzer_ids_str, USE_FUZZ_ID_SEP);
-                bool use_it = false;
-                while (fuzzer_id != NULL) {
-                    if (strcmp(fuzzer_id, driver->fuzzer_id) == 0) {
-                        use_it = true;
-                        break;
-                    }
-                    fuzzer_id = strtok(NULL, USE_FUZZ_ID_SEP);
+                bool used = maybe_use_input(driver, frames, frames_n);
+                free_frames(frames, frames_n);
+                if (!used) {
+                    ret = EXIT_FAILURE;
+                    break;
                 }
+            }
 
-                if (use_it) {
-                    LOG_I("using %s", input_path);
-                    if (!use_input(driver, input_path, coverage_path)) {
-                        LOG_F("failed to use input");
-                        ret = EXIT_FAILURE;
-                        break;
-                    }
-                }
+            // 4. look for the end of a generation -> tell the master the fuzzer is ready
+            if (driver->readiness_path != NULL && !maybe_send_ready(driver)) {
+                ret = EXIT_FAILURE;
+                break;
             }
 
             usleep(100);
         }
     }
 
+    if (!stop_fuzzer(driver))
+        ret = EXIT_FAILURE;
+
     close(inotify_fd);
     return ret;
 }
@@ -725,17 +1342,11 @@ free_driver(driver_t *driver)
         zmq_close(driver->use_sub);
     if (driver->metric_rep)
         zmq_close(driver->metric_rep);
+    if (driver->registration_req)
+        zmq_close(driver->registration_req);
 
-    if (driver->coverage_info) {
-        HashTableIter hti;
-        hashtable_iter_init(&hti, driver->coverage_info);
-        TableEntry *entry = NULL;
-        while (hashtable_iter_next(&hti, &entry) != CC_ITER_END) {
-            free(entry->key);
-            free(entry->value);
-        }
-        hashtable_destroy(driver->coverage_info);
-    }
+    if (driver->coverage_info)
+        free_hashtable(driver->coverage_info);
 
     if (driver->interesting_seen) {
         HashSetIter hsi;
@@ -754,13 +1365,46 @@ free_driver(driver_t *driver)
         close(driver->coverage_log_fd);
 
     if (driver->fuzzer_running && driver->fuzzer_pid > 0 &&
-        kill(driver->fuzzer_pid, SIGKILL) == -1)
+        kill(-driver->fuzzer_pid, SIGKILL) == -1)
         PLOG_W("failed to kill fuzzer (pid=%d)", driver->fuzzer_pid);
 
     free(driver);
 }
 
 
+// endpoints of the master's queues and of the metric server to bind, either given as such
+// ("interesting,use,metric") or made of the ports of the master host (a metric port of 0 lets
+// the system pick one)
+static bool
+parse_endpoints(const char *ports_str, const char *endpoints_str, const char *master_host,
+                char *int_endpoint, char *use_endpoint, char *metric_endpoint)
+{
+    if (endpoints_str != NULL) {
+        if (sscanf(endpoints_str, "%4095[^,],%4095[^,],%4095s",
+                   int_endpoint, use_endpoint, metric_endpoint) != 3) {
+            LOG_F("failed to parse endpoints '%s'", endpoints_str);
+            return false;
+        }
+        return true;
+    }
+
+    uint32_t port_int_input, port_use_input, port_metric;
+    if (sscanf(ports_str, "%" PRIu32 ",%" PRIu32 ",%" PRIu32,
+               &port_int_input, &port_use_input, &port_metric) != 3) {
+        LOG_F("failed to parse ports '%s'", ports_str);
+        return false;
+    }
+
+    sprintf(int_endpoint, "tcp://%s:%" PRIu32, master_host, port_int_input);
+    sprintf(use_endpoint, "tcp://%s:%" PRIu32, master_host, port_use_input);
+    if (port_metric == 0)
+        sprintf(metric_endpoint, "tcp://*:*");
+    else
+        sprintf(metric_endpoint, "tcp://*:%" PRIu32, port_metric);
+    return true;
+}
+
+
 static void
 usage(const char *progname)
 {
@@ -769,8 +1413,16 @@ usage(const char *progname)
            "\t-i fuzzer_id\n\t-f fuzzer_cmd\n\t[-b r2bb.sh]\n\t-c corpus\n"
            "\t-d data_path\n\t[-l fuzzer_log]\n\t[-L fuzzer_error_log]\n"
            "\t[-s .section]\n"
+           "\t[-W work_path]              (defaults to " WORK_PATH ")\n"
+           "\t[-I inline_limit]           (send contents instead of paths, up to this size)\n"
+           "\t[-g readiness_file]         (the fuzzer appends a line to it every generation)\n"
+           "\t[-r]                        (resume from a previous run)\n"
            "\t[-F input_filename]         (if SUT reads from a file)\n"
-           "\t[-p i,u,m -j inject_path]   (those are mandatory in multi mode)\n",
+           "\t[-p i,u,m -j inject_path]   (those are mandatory in multi mode, m can be 0)\n"
+           "\t[-E i,u,m]                  (endpoints instead of ports, e.g. ipc://path)\n"
+           "\t[-m master_host]            (defaults to " MASTER_HOST ")\n"
+           "\t[-R port -t type [-a host]] (register with the master, advertising the metric\n"
+           "\t                             server on host, by default the host name)\n",
            progname);
 }
 
@@ -781,6 +1433,11 @@ main(int argc, char const *argv[]) {
     char *sec_name = NULL;
     char *basic_block_script = NULL;
     char *queues_ports_str = NULL;
+    char *endpoints_str = NULL;
+    const char *master_host = MASTER_HOST;
+    const char *fuzzer_type = NULL;
+    const char *advertised_host = NULL;
+    uint32_t registration_port = 0;
 
     driver_t *driver = malloc(sizeof(driver_t));
     assert(driver != NULL);
@@ -788,9 +1445,11 @@ main(int argc, char const *argv[]) {
     driver->sut_use_stdin = true;
     driver->interesting_log_fd = -1;
     driver->coverage_log_fd = -1;
+    driver->work_path = WORK_PATH;
 
     int opt;
-    while ((opt = getopt(argc, (char * const*) argv, "i:f:s:b:c:p:d:l:L:j:F:")) != -1) {
+    const char *optstring = "i:f:s:b:c:p:E:d:l:L:j:F:W:I:g:rm:R:t:a:";
+    while ((opt = getopt(argc, (char * const*) argv, optstring)) != -1) {
         switch (opt) {
         case 'i':
             driver->fuzzer_id = optarg;
@@ -813,6 +1472,9 @@ main(int argc, char const *argv[]) {
         case 'p':
             queues_ports_str = optarg;
             break;
+        case 'E':
+            endpoints_str = optarg;
+            break;
         case 'd':
             driver->data_path = optarg;
             break;
@@ -829,24 +1491,61 @@ main(int argc, char const *argv[]) {
             driver->sut_input_file = optarg;
             driver->sut_use_stdin = false;
             break;
+        case 'W':
+            driver->work_path = optarg;
+            break;
+        case 'I':
+            driver->inline_limit = strtoul(optarg, NULL, 10);
+            break;
+        case 'g':
+            driver->readiness_path = optarg;
+            break;
+        case 'r':
+            driver->resume = true;
+            break;
+        case 'm':
+            master_host = optarg;
+            break;
+        case 'R':
+            registration_port = strtoul(optarg, NULL, 10);
+            break;
+        case 't':
+            fuzzer_type = optarg;
+            break;
+        case 'a':
+            advertised_host = optarg;
+            break;
         }
     }
 
     if (argc == optind || driver->fuzzer_id == NULL ||
-        driver->fuzzer_corpus_path == NULL || driver->data_path == NULL)
+        driver->fuzzer_corpus_path == NULL || driver->data_path == NULL ||
+        (registration_port != 0 && (fuzzer_type == NULL ||
+                                    (queues_ports_str == NULL && endpoints_str == NULL))))
     {
         free_driver(driver);
         usage(argv[0]);
         exit(EXIT_FAILURE);
     }
 
-    if (queues_ports_str == NULL || driver->inject_path == NULL) {
+    if ((queues_ports_str == NULL && endpoints_str == NULL) || driver->inject_path == NULL) {
         driver->single_mode = true;
     }
 
+    // contents received in inline mode are stored in the data path
+    if (driver->inline_limit > 0) {
+        char received_path[PATH_MAX];
+        snprintf(received_path, PATH_MAX - 1, "%s/" RECEIVED_DIR, driver->data_path);
+        if (mkdir(received_path, 0755) == -1 && errno != EEXIST) {
+            PLOG_F("failed to create %s", received_path);
+            free_driver(driver);
+            exit(EXIT_FAILURE);
+        }
+    }
+
     if (driver->sut_use_stdin) {
         char *tmp = malloc(PATH_MAX * sizeof(char));
-        snprintf(tmp, PATH_MAX - 1, "%s/.%s.input", WORK_PATH, driver->fuzzer_id);
+        snprintf(tmp, PATH_MAX - 1, "%s/.%s.input", driver->work_path, driver->fuzzer_id);
         driver->sut_input_file = tmp;
     }
 
@@ -902,11 +1601,11 @@ main(int argc, char const *argv[]) {
     int ret = EXIT_SUCCESS;
 
     void *context = NULL;
-    if (queues_ports_str == NULL) {
+    if (queues_ports_str == NULL && endpoints_str == NULL) {
         // open interesting and coverage log file
         char filename[PATH_MAX];
         snprintf(filename, PATH_MAX - 1, "%s/%s.interesting.log",
-            WORK_PATH, driver->fuzzer_id);
+            driver->work_path, driver->fuzzer_id);
         driver->interesting_log_fd = open(filename, O_CREAT | O_TRUNC | O_WRONLY, 0664);
         if (driver->interesting_log_fd == -1) {
             PLOG_F("failed to open %s", filename);
@@ -915,7 +1614,7 @@ main(int argc, char const *argv[]) {
         }
 
         snprintf(filename, PATH_MAX - 1, "%s/%s.coverage.log",
-            WORK_PATH, driver->fuzzer_id);
+            driver->work_path, driver->fuzzer_id);
         driver->coverage_log_fd = open(filename, O_CREAT | O_TRUNC | O_WRONLY, 0664);
         if (driver->coverage_log_fd == -1) {
             PLOG_F("failed to open %s", filename);
@@ -924,13 +1623,9 @@ main(int argc, char const *argv[]) {
         }
     } else {
         // init zmq connections
-        uint32_t port_int_input;
-        uint32_t port_use_input;
-        uint32_t port_metric;
-        ret = sscanf(queues_ports_str, "%" PRIu32 ",%" PRIu32 ",%" PRIu32,
-                         &port_int_input, &port_use_input, &port_metric);
-        if (ret != 3) {
-            LOG_F("failed to parse ports '%s'", queues_ports_str);
+        char int_endpoint[PATH_MAX], use_endpoint[PATH_MAX], metric_endpoint[PATH_MAX];
+        if (!parse_endpoints(queues_ports_str, endpoints_str, master_host,
+                             int_endpoint, use_endpoint, metric_endpoint)) {
             free_driver(driver);
             exit(EXIT_FAILURE);
         }
@@ -938,51 +1633,120 @@ main(int argc, char const *argv[]) {
         context = zmq_ctx_new();
         char buf[PATH_MAX];
 
-        driver->interesting_push = zmq_socket(context, ZMQ_PUSH);
-        sprintf(buf, "tcp://localhost:%d", port_int_input);
-        if (zmq_connect(driver->interesting_push, buf) == -1) {
-            PLOG_F("failed to connect to %s", buf);
+        // the master learns the actual endpoint of the metric server from the hello
+        driver->metric_rep = zmq_socket(context, ZMQ_REP);
+        if (zmq_bind(driver->metric_rep, metric_endpoint) == -1) {
+            if (errno == EADDRINUSE) {
+                LOG_F("failed to bind to %s: address already in use (by another campaign?)",
+                    metric_endpoint);
+            } else {
+                PLOG_F("failed to bind to %s", metric_endpoint);
+            }
             free_driver(driver);
             zmq_ctx_destroy(context);
             exit(EXIT_FAILURE);
         }
-        LOG_I("connected to interesting queue on %s", buf);
+        size_t endpoint_len = sizeof(metric_endpoint);
+        if (zmq_getsockopt(driver->metric_rep, ZMQ_LAST_ENDPOINT, metric_endpoint,
+                           &endpoint_len) == -1) {
+            PLOG_F("failed to get the metric server endpoint");
+            free_driver(driver);
+            zmq_ctx_destroy(context);
+            exit(EXIT_FAILURE);
+        }
+        LOG_I("bind metric server on %s", metric_endpoint);
+
+        if (registration_port != 0) {
+            // join the campaign of a master running elsewhere
+            char hostname[HOST_NAME_MAX + 1] = { '\0' };
+            if (advertised_host == NULL) {
+                gethostname(hostname, HOST_NAME_MAX);
+                advertised_host = hostname;
+            }
 
-        driver->use_sub = zmq_socket(context, ZMQ_SUB);
-        sprintf(buf, "tcp://localhost:%d", port_use_input);
-        if (zmq_connect(driver->use_sub, buf) == -1) {
-            PLOG_F("failed to connect to %s", buf);
+            int timeout = REGISTRATION_TIMEOUT_MS, linger = 0;
+            driver->registration_req = zmq_socket(context, ZMQ_REQ);
+            zmq_setsockopt(driver->registration_req, ZMQ_RCVTIMEO, &timeout, sizeof(timeout));
+            zmq_setsockopt(driver->registration_req, ZMQ_LINGER, &linger, sizeof(linger));
+            sprintf(buf, "tcp://%s:%" PRIu32, master_host, registration_port);
+            if (zmq_connect(driver->registration_req, buf) == -1) {
+                PLOG_F("failed to connect to %s", buf);
+                free_driver(driver);
+                zmq_ctx_destroy(context);
+                exit(EXIT_FAILURE);
+            }
+
+            // the metric server is advertised with the port it is bound to
+            char *port_str = strrchr(metric_endpoint, ':');
+            if (strncmp(metric_endpoint, "tcp://", 6) != 0 || port_str == NULL) {
+                LOG_F("registration needs a tcp metric server, not %s", metric_endpoint);
+                free_driver(driver);
+                zmq_ctx_destroy(context);
+                exit(EXIT_FAILURE);
+            }
+            char advertised_endpoint[PATH_MAX];
+            snprintf(advertised_endpoint, PATH_MAX - 1, "tcp://%s%s", advertised_host,
+                port_str);
+            const void *register_frames[] = {
+                PROTOCOL_VERSION, "register", driver->fuzzer_id, fuzzer_type,
+                advertised_endpoint
+            };
+            if (!registration_request(driver, register_frames, 5)) {
+                free_driver(driver);
+                zmq_ctx_destroy(context);
+                exit(EXIT_FAILURE);
+            }
+            LOG_I("registered on %s with metric server %s", buf, advertised_endpoint);
+        }
+
+        driver->interesting_push = zmq_socket(context, ZMQ_PUSH);
+        if (zmq_connect(driver->interesting_push, int_endpoint) == -1) {
+            PLOG_F("failed to connect to %s", int_endpoint);
             free_driver(driver);
             zmq_ctx_destroy(context);
             exit(EXIT_FAILURE);
         }
-        if (zmq_setsockopt(driver->use_sub, ZMQ_SUBSCRIBE,
-                           SUB_TOPIC, strlen(SUB_TOPIC)) == -1) {
-            PLOG_F("failed to set sockopt for use subscription");
+        LOG_I("connected to interesting queue on %s", int_endpoint);
+
+        // announce ourselves, so that the master can spot protocol mismatches early and knows
+        // where to request metrics
+        const void *hello_frames[] = {
+            PROTOCOL_VERSION, "hello", driver->fuzzer_id, metric_endpoint
+        };
+        if (!send_frames(driver->interesting_push, hello_frames, NULL, 4)) {
+            PLOG_F("failed to send hello");
             free_driver(driver);
             zmq_ctx_destroy(context);
             exit(EXIT_FAILURE);
         }
-        LOG_I("connected to use input queue on %s", buf);
 
-        driver->metric_rep = zmq_socket(context, ZMQ_REP);
-        sprintf(buf, "tcp://*:%d", port_metric);
-        if (zmq_bind(driver->metric_rep, buf) == -1) {
-            PLOG_F("failed to bind to %s", buf);
+        driver->use_sub = zmq_socket(context, ZMQ_SUB);
+        if (zmq_connect(driver->use_sub, use_endpoint) == -1) {
+            PLOG_F("failed to connect to %s", use_endpoint);
             free_driver(driver);
             zmq_ctx_destroy(context);
             exit(EXIT_FAILURE);
         }
-        LOG_I("bind metric server on %s", buf);
+        if (zmq_setsockopt(driver->use_sub, ZMQ_SUBSCRIBE,
+                           SUB_TOPIC, strlen(SUB_TOPIC)) == -1) {
+            PLOG_F("failed to set sockopt for use subscription");
+            free_driver(driver);
+            zmq_ctx_destroy(context);
+            exit(EXIT_FAILURE);
+        }
+        LOG_I("connected to use input queue on %s", use_endpoint);
     }
 
     if (hashset_new(&driver->interesting_seen) == CC_OK) {
         if (hashtable_new(&driver->coverage_info) != CC_OK) {
             LOG_I("failed to create coverage_info");
             ret = EXIT_FAILURE;
+        } else if (driver->resume && !resume_driver(driver)) {
+            LOG_F("failed to resume");
+            ret = EXIT_FAILURE;
         } else {
             signal(SIGINT, sig_handler);
-            signal(SIGKILL, sig_handler);
+            signal(SIGTERM, sig_handler);
             ret = driver_loop(driver);
         }
     } else {
@@ -990,6 +1754,12 @@ main(int argc, char const *argv[]) {
         ret = EXIT_FAILURE;
     }
 
+    // leave the campaign (failures are logged, there is nothing else to do about them)
+    if (driver->registration_req) {
+        const void *unregister_frames[] = { PROTOCOL_VERSION, "unregister", driver->fuzzer_id };
+        registration_request(driver, unregister_frames, 3);
+    }
+
     free_driver(driver);
     if (context != NULL)
         zmq_ctx_destroy(context);
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use config::AdapterConfig;
+
+
+// the peer AFL instances sync from, where drivers inject inputs
+const AFL_INJECT_PEER: &str = "inject";
+
+
+// paths set on the command line of a fuzzer, relative to the working directory, taking the place
+// of the ones of its type
+#[derive(Debug, Default)]
+pub struct CommandLayout {
+    pub corpus: Option<String>,
+    pub inject: Option<String>,
+    pub crashes: Option<String>,
+    // prefix of the crash file names
+    pub crash_prefix: Option<String>,
+    pub stats: Option<String>
+}
+
+
+// value of an option of the fuzzer, not of the target following "--"
+fn option_value<'a>(command: &'a [String], option: &str) -> Option<&'a str> {
+    command.iter().take_while(|a| *a != "--").skip_while(|a| *a != option).nth(1)
+        .map(|v| v.as_str())
+}
+
+
+// what the master needs to know about a fuzzer, paths are relative to the directory of the
+// fuzzer in the work directory
+pub trait FuzzerAdapter: Debug {
+    // the type in id.type.conf files and campaign files
+    fn name(&self) -> &str;
+
+    // where the fuzzer keeps the inputs it finds, watched by the driver
+    fn corpus_path(&self, fuzzer_id: &str) -> String;
+
+    // where the driver puts inputs for the fuzzer to pick up
+    fn inject_path(&self, fuzzer_id: &str) -> String;
+
+    // where crashing inputs are stored
+    fn crash_path(&self, fuzzer_id: &str) -> String;
+
+    // tell crashing inputs apart from other files in the crash directory
+    fn is_crash_file(&self, _filename: &str) -> bool { true }
+
+    // paths of the fuzzer its command line decides
+    fn command_layout(&self, _command: &[String]) -> CommandLayout { CommandLayout::default() }
+
+    // file the fuzzer appends a line to at the end of every generation. Such fuzzers are
+    // batched: they get the best inputs collected since their last generation instead of taking
+    // part in the winning strategy.
+    fn readiness_path(&self, _fuzzer_id: &str) -> Option<String> { None }
+
+    // file with the statistics of the fuzzer
+    fn stats_path(&self, _fuzzer_id: &str) -> Option<String> { None }
+
+    // statistics as AFL writes them, one "key : value" per line
+    fn parse_stats(&self, contents: &str) -> HashMap<String, String> {
+        contents.lines().filter_map(|line| {
+            let mut split = line.splitn(2, ':');
+            match (split.next(), split.next()) {
+                (Some(key), Some(value)) => {
+                    Some((key.trim().to_string(), value.trim().to_string()))
+                },
+                _ => None
+            }
+        }).collect()
+    }
+
+    // command line continuing from the state the fuzzer left behind, if it needs a different one
+    fn resume_command(&self, _command: &[String]) -> Option<Vec<String>> { None }
+
+    // command line of another instance of the fuzzer, sharing its findings with this one
+    fn secondary_command(&self, _command: &[String], _instance: &str) -> Option<Vec<String>> {
+        None
+    }
+}
+
+
+// AFL and its forks, the layout follows the output directory (-o) and the instance name (-M or
+// -S), which AFL++ defaults to "default" and AFL leaves out of the paths
+#[derive(Debug)]
+pub struct AflAdapter {
+    name: &'static str,
+    default_instance: Option<&'static str>
+}
+
+impl FuzzerAdapter for AflAdapter {
+    fn name(&self) -> &str { self.name }
+
+    fn corpus_path(&self, fuzzer_id: &str) -> String { format!("out/{}/queue", fuzzer_id) }
+
+    fn inject_path(&self, _fuzzer_id: &str) -> String { "out/inject/queue".to_string() }
+
+    fn crash_path(&self, fuzzer_id: &str) -> String { format!("out/{}/crashes", fuzzer_id) }
+
+    fn is_crash_file(&self, filename: &str) -> bool { filename.starts_with("id:") }
+
+    fn stats_path(&self, fuzzer_id: &str) -> Option<String> {
+        Some(format!("out/{}/fuzzer_stats", fuzzer_id))
+    }
+
+    fn command_layout(&self, command: &[String]) -> CommandLayout {
+        let out_path = match option_value(command, "-o") {
+            Some(out_path) => out_path,
+            None => return CommandLayout::default()
+        };
+
+        let instance = option_value(command, "-M").or(option_value(command, "-S"))
+            .or(self.default_instance);
+        let instance_path = match instance {
+            Some(instance) => format!("{}/{}", out_path, instance),
+            None => out_path.to_string()
+        };
+
+        CommandLayout {
+            corpus: Some(format!("{}/queue", instance_path)),
+            inject: Some(format!("{}/{}/queue", out_path, AFL_INJECT_PEER)),
+            crashes: Some(format!("{}/crashes", instance_path)),
+            crash_prefix: None,
+            stats: Some(format!("{}/fuzzer_stats", instance_path))
+        }
+    }
+
+    // AFL refuses to reuse an output directory unless told to resume with '-i -'
+    fn resume_command(&self, command: &[String]) -> Option<Vec<String>> {
+        let mut input_dir_next = false;
+        Some(command.iter().map(|arg| {
+            let arg_resume = if input_dir_next { "-".to_string() } else { arg.clone() };
+            input_dir_next = arg == "-i";
+            arg_resume
+        }).collect())
+    }
+
+    // a -S instance in the same output directory, syncing with the others
+    fn secondary_command(&self, command: &[String], instance: &str) -> Option<Vec<String>> {
+        let target_start = command.iter().position(|a| a == "--").unwrap_or(command.len());
+        let mut secondary = vec![];
+        let mut instance_next = false;
+        for (i, arg) in command.iter().enumerate() {
+            if instance_next {
+                instance_next = false;
+            } else if i < target_start && (arg == "-M" || arg == "-S") {
+                instance_next = true;
+            } else {
+                secondary.push(arg.clone());
+            }
+        }
+
+        // right after the executable
+        let options_start = secondary.len().min(1);
+        secondary.insert(options_start, instance.to_string());
+        secondary.insert(options_start, "-S".to_string());
+        Some(secondary)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct HonggfuzzAdapter;
+
+impl FuzzerAdapter for HonggfuzzAdapter {
+    fn name(&self) -> &str { "hongg" }
+
+    fn corpus_path(&self, _fuzzer_id: &str) -> String { "in".to_string() }
+
+    fn inject_path(&self, _fuzzer_id: &str) -> String { "out/inject".to_string() }
+
+    fn crash_path(&self, fuzzer_id: &str) -> String { format!("out/{}", fuzzer_id) }
+
+    fn is_crash_file(&self, filename: &str) -> bool { filename.starts_with("SIG") }
+}
+
+
+#[derive(Debug)]
+pub struct VUzzerAdapter;
+
+impl FuzzerAdapter for VUzzerAdapter {
+    fn name(&self) -> &str { "vu" }
+
+    fn corpus_path(&self, _fuzzer_id: &str) -> String { "special".to_string() }
+
+    fn inject_path(&self, _fuzzer_id: &str) -> String { "special".to_string() }
+
+    fn crash_path(&self, _fuzzer_id: &str) -> String { "outd/crashInputs".to_string() }
+
+    fn readiness_path(&self, _fuzzer_id: &str) -> Option<String> {
+        Some("stats.log".to_string())
+    }
+}
+
+
+// libFuzzer writes new inputs to the first corpus directory of its command line and, with
+// -reload=1, picks up the files others add there, which makes it the inject directory as well
+#[derive(Debug)]
+pub struct LibFuzzerAdapter;
+
+impl FuzzerAdapter for LibFuzzerAdapter {
+    fn name(&self) -> &str { "libfuzzer" }
+
+    fn corpus_path(&self, _fuzzer_id: &str) -> String { "corpus".to_string() }
+
+    fn inject_path(&self, _fuzzer_id: &str) -> String { "corpus".to_string() }
+
+    fn crash_path(&self, _fuzzer_id: &str) -> String { "crashes".to_string() }
+
+    // timeouts and out-of-memory artifacts are not counted, as AFL does with hangs
+    fn is_crash_file(&self, filename: &str) -> bool {
+        filename.starts_with("crash-") || filename.starts_with("leak-")
+    }
+
+    // the corpus directory is the first argument that is not a -flag=value one, artifacts are
+    // named after -artifact_prefix, which defaults to the working directory
+    fn command_layout(&self, command: &[String]) -> CommandLayout {
+        let arguments: Vec<_> = command.iter().skip(1).take_while(|a| *a != "--").collect();
+        let corpus = arguments.iter().find(|a| !a.starts_with('-'))
+            .map(|c| c.trim_end_matches('/').to_string());
+
+        let prefix = arguments.iter().filter_map(|arg| {
+            let mut split = arg.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some("-artifact_prefix"), Some(prefix)) => Some(prefix),
+                _ => None
+            }
+        }).next_back().unwrap_or("");
+
+        let (crashes, crash_prefix) = match prefix.rfind('/') {
+            Some(i) => (prefix[..i + 1].to_string(), prefix[i + 1..].to_string()),
+            None => (".".to_string(), prefix.to_string())
+        };
+        CommandLayout {
+            inject: corpus.clone(),
+            corpus,
+            crashes: Some(crashes),
+            crash_prefix: Some(crash_prefix),
+            ..CommandLayout::default()
+        }
+    }
+}
+
+
+// a fuzzer whose directory layout is declared in the campaign file, "{id}" in paths is replaced
+// by the fuzzer id
+#[derive(Debug)]
+pub struct ConfigAdapter {
+    config: AdapterConfig
+}
+
+impl ConfigAdapter {
+    fn path_for(path: &str, fuzzer_id: &str) -> String { path.replace("{id}", fuzzer_id) }
+}
+
+impl FuzzerAdapter for ConfigAdapter {
+    fn name(&self) -> &str { &self.config.name }
+
+    fn corpus_path(&self, fuzzer_id: &str) -> String {
+        ConfigAdapter::path_for(&self.config.corpus, fuzzer_id)
+    }
+
+    fn inject_path(&self, fuzzer_id: &str) -> String {
+        ConfigAdapter::path_for(&self.config.inject, fuzzer_id)
+    }
+
+    fn crash_path(&self, fuzzer_id: &str) -> String {
+        ConfigAdapter::path_for(&self.config.crashes, fuzzer_id)
+    }
+
+    fn is_crash_file(&self, filename: &str) -> bool {
+        self.config.crash_prefix.as_ref().map(|p| filename.starts_with(p)).unwrap_or(true)
+    }
+
+    fn readiness_path(&self, fuzzer_id: &str) -> Option<String> {
+        self.config.readiness.as_ref().map(|p| ConfigAdapter::path_for(p, fuzzer_id))
+    }
+
+    fn stats_path(&self, fuzzer_id: &str) -> Option<String> {
+        self.config.stats.as_ref().map(|p| ConfigAdapter::path_for(p, fuzzer_id))
+    }
+}
+
+
+// the built-in adapters and the ones declared in the campaign file, by name
+pub struct Adapters {
+    adapters: HashMap<String, Rc<dyn FuzzerAdapter>>
+}
+
+impl Adapters {
+    pub fn from_config(configs: &[AdapterConfig]) -> Result<Adapters, String> {
+        let mut adapters: HashMap<String, Rc<dyn FuzzerAdapter>> = HashMap::new();
+        let builtins: Vec<Rc<dyn FuzzerAdapter>> =
+            vec![Rc::new(AflAdapter { name: "afl", default_instance: None }),
+                 Rc::new(AflAdapter { name: "aflpp", default_instance: Some("default") }),
+                 Rc::new(HonggfuzzAdapter), Rc::new(VUzzerAdapter), Rc::new(LibFuzzerAdapter)];
+        for adapter in builtins {
+            adapters.insert(adapter.name().to_string(), adapter);
+        }
+
+        for config in configs {
+            if adapters.contains_key(&config.name) {
+                return Err(format!("fuzzer type {} is defined more than once", config.name));
+            }
+            adapters.insert(config.name.clone(), Rc::new(ConfigAdapter { config: config.clone() }));
+        }
+
+        Ok(Adapters { adapters })
+    }
+
+    pub fn get(&self, name: &str) -> Result<Rc<dyn FuzzerAdapter>, String> {
+        self.adapters.get(name).cloned().ok_or_else(|| {
+            let mut names: Vec<_> = self.adapters.keys().map(|n| n.as_str()).collect();
+            names.sort();
+            format!("unknown fuzzer type {} (one of {})", name, names.join(", "))
+        })
+    }
+}
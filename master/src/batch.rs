@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+
+use time::Duration;
+
+use messages::{InterestingInput, RepMetric};
+
+
+pub const DEFAULT_BATCH_SIZE: usize = 1;
+
+
+// inputs collected for a batched fuzzer until it is ready for new ones, the best of which it
+// then gets all at once
+pub struct Batch {
+    size: usize,
+    // inputs collected longer ago are not worth delivering anymore
+    max_age: Option<Duration>,
+    high: bool,
+    pending: Vec<Pending>,
+    pub ready: bool,
+    // the driver sends ready messages, otherwise modifications of the readiness file seen
+    // through inotify are counted instead
+    pub notified: bool,
+    pub modified: usize
+}
+
+struct Pending {
+    input: InterestingInput,
+    metric: RepMetric,
+    signature: Option<u64>,
+    received: Duration
+}
+
+impl Batch {
+    pub fn new(size: usize, max_age: Option<Duration>, high: bool) -> Batch {
+        Batch {
+            size,
+            max_age,
+            high,
+            pending: vec![],
+            ready: false,
+            notified: false,
+            modified: 0
+        }
+    }
+
+    // an input with the same coverage signature as a collected one only takes its place if its
+    // metric is better
+    pub fn add(&mut self, input: InterestingInput, metric: RepMetric, signature: Option<u64>,
+        received: Duration)
+    {
+        self.drop_stale(received);
+
+        let high = self.high;
+        let same = signature.and_then(|s| self.pending.iter_mut().find(|p| p.signature == Some(s)));
+        let pending = Pending {
+            input,
+            metric,
+            signature,
+            received
+        };
+        match same {
+            Some(p) => {
+                let better = if high { pending.metric.metric > p.metric.metric }
+                    else { pending.metric.metric < p.metric.metric };
+                if better {
+                    *p = pending;
+                }
+            },
+            None => self.pending.push(pending)
+        }
+    }
+
+    // the best inputs collected (with their coverage signature), best first, once some are there
+    // the batch is no longer ready
+    pub fn take(&mut self, now: Duration) -> Vec<(InterestingInput, RepMetric, Option<u64>)> {
+        self.drop_stale(now);
+        if self.pending.is_empty() {
+            return vec![];
+        }
+
+        let high = self.high;
+        self.pending.sort_by(|p1, p2| {
+            let order = p1.metric.metric.partial_cmp(&p2.metric.metric)
+                .unwrap_or(Ordering::Equal);
+            if high { order.reverse() } else { order }
+        });
+
+        self.ready = false;
+        let size = self.size;
+        self.pending.drain(..).take(size).map(|p| (p.input, p.metric, p.signature)).collect()
+    }
+
+    // inputs collected so far
+    pub fn len(&self) -> usize { self.pending.len() }
+
+    // start over with a restarted driver
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.ready = false;
+        self.modified = 0;
+    }
+
+    fn drop_stale(&mut self, now: Duration) {
+        if let Some(max_age) = self.max_age {
+            self.pending.retain(|p| now - p.received <= max_age);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str) -> InterestingInput {
+        InterestingInput {
+            fuzzer_id: "sender".to_string(),
+            input_path: name.to_string(),
+            coverage_path: format!("{}.cov", name),
+            data: None
+        }
+    }
+
+    fn add(batch: &mut Batch, name: &str, metric: f64, signature: Option<u64>, received: i64) {
+        batch.add(input(name), RepMetric::new(vec![metric], &[1.0]), signature,
+            Duration::seconds(received));
+    }
+
+    fn take(batch: &mut Batch, now: i64) -> Vec<String> {
+        batch.take(Duration::seconds(now)).into_iter().map(|t| t.0.input_path).collect()
+    }
+
+    #[test]
+    fn take_flushes_the_best_up_to_size() {
+        let mut batch = Batch::new(2, None, true);
+        add(&mut batch, "a", 1.0, None, 0);
+        add(&mut batch, "b", 3.0, None, 0);
+        add(&mut batch, "c", 2.0, None, 0);
+        batch.ready = true;
+
+        assert_eq!(take(&mut batch, 0), vec!["b", "c"]);
+        assert!(!batch.ready);
+        assert_eq!(batch.len(), 0);
+        assert!(take(&mut batch, 0).is_empty());
+    }
+
+    #[test]
+    fn take_orders_low_metrics_first() {
+        let mut batch = Batch::new(2, None, false);
+        add(&mut batch, "a", 1.0, None, 0);
+        add(&mut batch, "b", 3.0, None, 0);
+        add(&mut batch, "c", 2.0, None, 0);
+
+        assert_eq!(take(&mut batch, 0), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn take_of_nothing_keeps_the_batch_ready() {
+        let mut batch = Batch::new(1, None, true);
+        batch.ready = true;
+
+        assert!(take(&mut batch, 0).is_empty());
+        assert!(batch.ready);
+    }
+
+    #[test]
+    fn add_keeps_the_better_of_the_same_signature() {
+        let mut batch = Batch::new(5, None, true);
+        add(&mut batch, "a", 1.0, Some(1), 0);
+        add(&mut batch, "b", 2.0, Some(1), 0);
+        add(&mut batch, "c", 0.5, Some(1), 0);
+        add(&mut batch, "d", 0.5, None, 0);
+        add(&mut batch, "e", 0.5, None, 0);
+
+        assert_eq!(batch.len(), 3);
+        assert_eq!(take(&mut batch, 0), vec!["b", "d", "e"]);
+    }
+
+    #[test]
+    fn stale_inputs_are_dropped() {
+        let mut batch = Batch::new(5, Some(Duration::seconds(10)), true);
+        add(&mut batch, "a", 3.0, None, 0);
+        add(&mut batch, "b", 2.0, None, 5);
+        // exactly as old as allowed is not stale yet
+        add(&mut batch, "c", 1.0, None, 10);
+        assert_eq!(batch.len(), 3);
+
+        add(&mut batch, "d", 0.5, None, 11);
+        assert_eq!(batch.len(), 3);
+
+        assert_eq!(take(&mut batch, 16), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut batch = Batch::new(5, None, true);
+        add(&mut batch, "a", 1.0, None, 0);
+        batch.ready = true;
+        batch.modified = 2;
+
+        batch.reset();
+        assert_eq!(batch.len(), 0);
+        assert!(!batch.ready);
+        assert_eq!(batch.modified, 0);
+    }
+}
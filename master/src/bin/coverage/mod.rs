@@ -17,6 +17,12 @@ pub fn read_structs<T, P: AsRef<Path>>(path: P) -> io::Result<Vec<T>> {
     let path = path.as_ref();
     let struct_size = mem::size_of::<T>();
     let num_bytes = fs::metadata(path)?.len() as usize;
+    // a truncated or foreign file would be read past the end of the vector
+    let stray_bytes = num_bytes % struct_size;
+    if stray_bytes != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("size of {} ({} bytes) \
+            is not a multiple of {}", path.display(), num_bytes, struct_size)));
+    }
     let num_structs = num_bytes / struct_size;
     let mut reader = BufReader::new(File::open(path)?);
     let mut r = Vec::<T>::with_capacity(num_structs);
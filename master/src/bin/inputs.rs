@@ -153,6 +153,7 @@ fn main() {
 
     let mut opts = Options::new();
     opts.optflag("h", "help", "Print this help");
+    opts.optopt("W", "work-dir", "The work directory the other paths default to", WORK_PATH);
     opts.optopt("f", "file", "The inputs.log file to analyze",
         format!("{}/inputs.log", WORK_PATH).as_str());
     opts.optopt("t", "time-unit", "The time unit to use to sample coverage", "1000");
@@ -164,17 +165,21 @@ fn main() {
     let args: Vec<_> = env::args().collect();
     let matches = opts.parse(&args[1..]).map_err(|f| f.to_string()).unwrap();
 
-    if matches.opt_present("h") || !matches.opt_present("f") ||
-        !matches.opt_present("i") || !matches.opt_present("c")
+    if matches.opt_present("h") || !(matches.opt_present("W") || (matches.opt_present("f") &&
+        matches.opt_present("i") && matches.opt_present("c")))
     {
         println!("{}", opts.usage(&format!("Usage: {} [options]", args[0])));
         return;
     }
 
+    let work_path = matches.opt_str("W").unwrap_or(WORK_PATH.to_string());
     let time_unit = matches.opt_str("t").map(|s| s.parse().unwrap());
-    let filename = matches.opt_str("f").unwrap();
-    let coverage_filename = matches.opt_str("c").unwrap();
-    let interesting_filename = matches.opt_str("i").unwrap();
+    let filename = matches.opt_str("f")
+        .unwrap_or(format!("{}/inputs.log", work_path));
+    let coverage_filename = matches.opt_str("c")
+        .unwrap_or(format!("{}/coverage.log", work_path));
+    let interesting_filename = matches.opt_str("i")
+        .unwrap_or(format!("{}/interesting.log", work_path));
 
     if let Err(e) = process_file(&filename, &coverage_filename, &interesting_filename, time_unit) {
         error!("{}", e);
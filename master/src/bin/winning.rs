@@ -18,6 +18,9 @@ use common_m::{LOG_LINE_SEPARATOR, WORK_PATH};
 mod common;
 use common::*;
 
+// between the receivers of an input
+const WINNERS_SEPARATOR: &str = ",";
+
 
 // format is time,fuzzer_ids,winning
 fn parse_line(line: &String) -> Result<(u64, &str, Vec<&str>), String> {
@@ -30,7 +33,7 @@ fn parse_line(line: &String) -> Result<(u64, &str, Vec<&str>), String> {
         format!("failed parsing time from '{}': {}", line, e)
     })?;
     let fuzzer_id = splitted[1].trim();
-    let winning_ids: Vec<_> = splitted[2].trim().split("_").collect();
+    let winning_ids: Vec<_> = splitted[2].trim().split(WINNERS_SEPARATOR).collect();
 
     Ok((time_millis, fuzzer_id, winning_ids))
 }
@@ -132,6 +135,7 @@ fn main() {
 
     let mut opts = Options::new();
     opts.optflag("h", "help", "Print this help");
+    opts.optopt("W", "work-dir", "The work directory the other paths default to", WORK_PATH);
     opts.optopt("f", "file", "The winning.log file to analyze",
         format!("{}/winning.log", WORK_PATH).as_str());
     opts.optopt("t", "time-unit", "The time unit to use to sample data", "1000");
@@ -143,17 +147,21 @@ fn main() {
     let args: Vec<_> = env::args().collect();
     let matches = opts.parse(&args[1..]).map_err(|f| f.to_string()).unwrap();
 
-    if matches.opt_present("h") || !matches.opt_present("f") ||
-        !matches.opt_present("a") || !matches.opt_present("w")
+    if matches.opt_present("h") || !(matches.opt_present("W") || (matches.opt_present("f") &&
+        matches.opt_present("a") && matches.opt_present("w")))
     {
         println!("{}", opts.usage(&format!("Usage: {} [options]", args[0])));
         return;
     }
 
+    let work_path = matches.opt_str("W").unwrap_or(WORK_PATH.to_string());
     let time_unit = matches.opt_str("t").map(|s| s.parse().unwrap());
-    let filename = matches.opt_str("f").unwrap();
-    let accepted_filename = matches.opt_str("a").unwrap();
-    let won_filename = matches.opt_str("w").unwrap();
+    let filename = matches.opt_str("f")
+        .unwrap_or(format!("{}/winning.log", work_path));
+    let accepted_filename = matches.opt_str("a")
+        .unwrap_or(format!("{}/accepted.log", work_path));
+    let won_filename = matches.opt_str("w")
+        .unwrap_or(format!("{}/won.log", work_path));
 
     if let Err(e) = process_file(&filename, &accepted_filename, &won_filename, time_unit) {
         error!("{}", e);
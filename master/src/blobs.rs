@@ -0,0 +1,64 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+use fnv;
+
+
+// inputs and coverage received inside messages, stored under the work directory and named after
+// the hash of their contents so that each one is stored once
+pub struct BlobStore {
+    path: String,
+    limit: usize
+}
+
+impl BlobStore {
+    pub fn new(path: String, limit: usize) -> Result<BlobStore, String> {
+        fs::create_dir_all(&path).map_err(|e| {
+            format!("failed to create blob directory {}: {}", path, e)
+        })?;
+
+        Ok(BlobStore { path, limit })
+    }
+
+    // maximum size of the contents travelling inside a message
+    pub fn limit(&self) -> usize { self.limit }
+
+    // store a blob, returning its path and whether it was not stored already. Different contents
+    // with the same hash are told apart by a suffix.
+    pub fn store(&self, data: &[u8], extension: &str) -> Result<(String, bool), String> {
+        let hash = fnv::hash_bytes(data);
+        let mut collisions = 0;
+        loop {
+            let blob_path = match collisions {
+                0 => format!("{}/{:016x}.{}", self.path, hash, extension),
+                n => format!("{}/{:016x}-{}.{}", self.path, hash, n, extension)
+            };
+
+            let stored_len = match fs::metadata(&blob_path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => return self.write(blob_path, data)
+            };
+            if stored_len == data.len() as u64 && self.load(&blob_path)? == data {
+                return Ok((blob_path, false));
+            }
+            collisions += 1;
+        }
+    }
+
+    fn write(&self, blob_path: String, data: &[u8]) -> Result<(String, bool), String> {
+        File::create(&blob_path).and_then(|mut f| f.write_all(data)).map_err(|e| {
+            format!("failed to write blob {}: {}", blob_path, e)
+        })?;
+
+        Ok((blob_path, true))
+    }
+
+    pub fn load(&self, blob_path: &str) -> Result<Vec<u8>, String> {
+        let mut data = vec![];
+        File::open(blob_path).and_then(|mut f| f.read_to_end(&mut data)).map_err(|e| {
+            format!("failed to read blob {}: {}", blob_path, e)
+        })?;
+
+        Ok(data)
+    }
+}
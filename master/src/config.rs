@@ -0,0 +1,276 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+
+use time::Duration;
+
+use toml;
+
+use common::WORK_PATH;
+
+
+// campaign description, loaded from a TOML file and/or built from command line flags
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CampaignConfig {
+    pub work_dir: Option<String>,
+    #[serde(default)]
+    pub sut: Vec<String>,
+    #[serde(default)]
+    pub stdin: bool,
+    pub basic_blocks: Option<String>,
+    pub section: Option<String>,
+    pub metric_timeout: Option<u64>,
+    pub max_restarts: Option<u32>,
+    pub grace_period: Option<u64>,
+    pub inline: Option<usize>,
+    // compute metrics on the master from coverage files, instead of asking drivers
+    #[serde(default)]
+    pub local_metric: bool,
+    // names of the metrics inputs are evaluated with, and how they weigh in the one strategies use
+    #[serde(default)]
+    pub metrics: Vec<String>,
+    pub metric_weights: Option<Vec<f64>>,
+    // tcp (default) or ipc, for campaigns on a single host
+    pub transport: Option<String>,
+    // only set from the command line, a campaign file describes a campaign and not a run of it
+    #[serde(skip)]
+    pub resume: bool,
+    // print a line per input even on a terminal, instead of the status screen
+    #[serde(skip)]
+    pub plain: bool,
+    #[serde(default)]
+    pub winning: WinningConfig,
+    #[serde(default)]
+    pub ports: PortsConfig,
+    #[serde(default)]
+    pub stop: StopConfig,
+    #[serde(default, rename = "fuzzer")]
+    pub fuzzers: Vec<FuzzerConfig>,
+    #[serde(default, rename = "adapter")]
+    pub adapters: Vec<AdapterConfig>
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WinningConfig {
+    #[serde(default)]
+    pub high: bool,
+    pub threshold: Option<f64>,
+    pub top_k: Option<usize>,
+    pub proportional: Option<usize>,
+    pub ucb_window: Option<u64>
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortsConfig {
+    pub interesting: Option<u32>,
+    #[serde(rename = "use")]
+    pub use_: Option<u32>,
+    pub metric_start: Option<u32>,
+    // drivers running elsewhere can join the campaign through this port
+    pub registration: Option<u32>
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StopConfig {
+    pub time_budget: Option<String>,
+    pub max_idle: Option<String>,
+    pub coverage_plateau: Option<String>,
+    pub crashes: Option<usize>
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FuzzerConfig {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub fuzzer_type: String,
+    pub command: Option<Vec<String>>,
+    pub corpus: Option<String>,
+    pub inject: Option<String>,
+    pub metric_port: Option<u32>,
+    // instances run alongside this one (AFL -S), taking part in the campaign as one fuzzer
+    pub secondaries: Option<usize>,
+    // streaming (inputs are sent as they are found) or batched (the best ones are sent when the
+    // fuzzer is ready), batched by default if there is a readiness file
+    pub delivery: Option<String>,
+    // relative to the fuzzer directory, in place of the one of its type
+    pub readiness: Option<String>,
+    // how many inputs a batched fuzzer gets at once, and how old they can be
+    pub batch_size: Option<usize>,
+    pub batch_max_age: Option<String>,
+    // metric endpoint of a driver started elsewhere, such a driver is not spawned by the master
+    pub endpoint: Option<String>
+}
+
+
+// a fuzzer type described by its directory layout, paths are relative to the fuzzer directory
+// and "{id}" is replaced by the fuzzer id
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdapterConfig {
+    pub name: String,
+    pub corpus: String,
+    pub inject: String,
+    pub crashes: String,
+    // files in the crash directory not starting with it are not crashes
+    pub crash_prefix: Option<String>,
+    // file the fuzzer appends a line to every generation, making it a batched fuzzer
+    pub readiness: Option<String>,
+    pub stats: Option<String>
+}
+
+
+impl CampaignConfig {
+    pub fn from_file(filename: &str) -> Result<CampaignConfig, String> {
+        let mut contents = String::new();
+        File::open(filename).and_then(|mut f| f.read_to_string(&mut contents)).map_err(|e| {
+            format!("failed to read campaign file {}: {}", filename, e)
+        })?;
+
+        toml::from_str(&contents).map_err(|e| {
+            format!("failed to parse campaign file {}: {}", filename, e)
+        })
+    }
+
+    pub fn get_work_path(&self) -> String {
+        self.work_dir.clone().unwrap_or(WORK_PATH.to_string())
+    }
+
+    pub fn find_fuzzer(&self, fuzzer_id: &str) -> Option<&FuzzerConfig> {
+        self.fuzzers.iter().find(|f| f.id == fuzzer_id)
+    }
+}
+
+
+// parse durations like 90s, 30m, 4h or 1d (plain numbers are seconds)
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit) = match s.chars().last() {
+        Some(c) if c.is_alphabetic() => (&s[..s.len() - c.len_utf8()], c),
+        _ => (s, 's')
+    };
+
+    let n: i64 = number.parse().map_err(|e| {
+        format!("unable to parse {} as duration: {}", s, e)
+    })?;
+
+    let unit_secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' in duration {}", unit, s))
+    };
+
+    // time panics on durations it cannot represent
+    n.checked_mul(unit_secs).and_then(|secs| secs.checked_mul(1000)).map(Duration::milliseconds)
+        .ok_or_else(|| format!("duration {} is too long", s))
+}
+
+
+// collect .conf files from the work directory (hidden ones are generated by the master)
+pub fn find_conf_files(work_path: &str) -> Result<Vec<PathBuf>, String> {
+    let work_readdir = fs::read_dir(work_path).map_err(|e| {
+        format!("failed to read directory {}: {}", work_path, e)
+    })?;
+
+    let mut conf_files = vec![];
+    for entry in work_readdir {
+        let path = entry.map_err(|e| e.to_string())?.path();
+
+        let valid_file = {
+            let ext_opt = path.extension();
+            let hidden = path.file_name().map(|n| n.to_string_lossy().starts_with("."))
+                .unwrap_or(false);
+            path.is_file() && !hidden && ext_opt.is_some() && ext_opt.unwrap() == "conf"
+        };
+
+        if valid_file {
+            conf_files.push(path);
+        }
+    }
+
+    debug!("found conf files: {:?}", conf_files);
+
+    Ok(conf_files)
+}
+
+
+// build a fuzzer configuration from an id.type.conf file in the work directory
+pub fn discover_fuzzer(fuzzer_id: &str, conf_files: &[PathBuf], work_path: &str)
+    -> Result<FuzzerConfig, String>
+{
+    // find conf file starting with this fuzzer id
+    let conf_path_opt = conf_files.iter().find(|p| {
+        p.to_str().unwrap().contains(fuzzer_id)
+    });
+
+    if conf_path_opt.is_none() {
+        let e = format!("a config file for {} was not found in {}", fuzzer_id, work_path);
+        return Err(e);
+    }
+
+    let conf_path = conf_path_opt.unwrap();
+    // parse fuzzer type from conf filename
+    let conf_filename = conf_path.file_name().unwrap().to_string_lossy();
+    let conf_filename_split: Vec<_> = conf_filename.split(".").collect();
+    if conf_filename_split.len() < 3 {
+        return Err(format!("invalid conf filename {}", conf_path.display()));
+    }
+
+    // the type is checked against the known adapters along with the ones of the campaign file
+    Ok(FuzzerConfig {
+        id: fuzzer_id.to_string(),
+        fuzzer_type: conf_filename_split[1].to_string(),
+        command: None,
+        corpus: None,
+        inject: None,
+        metric_port: None,
+        secondaries: None,
+        delivery: None,
+        readiness: None,
+        batch_size: None,
+        batch_max_age: None,
+        endpoint: None
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration(" 90s "), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("4h"), Ok(Duration::hours(4)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+    }
+
+    #[test]
+    fn parse_duration_bad_suffixes() {
+        assert!(parse_duration("10w").is_err());
+        assert!(parse_duration("10ms").is_err());
+        assert!(parse_duration("10é").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn parse_duration_overflow() {
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert!(parse_duration(&format!("{}s", i64::MAX)).is_err());
+        assert!(parse_duration(&format!("{}d", i64::MAX / 1000)).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+
+use time::Duration;
+
+use common::LOG_LINE_SEPARATOR;
+use coverage::{Branch, read_structs};
+use fnv::FnvHasher;
+
+
+// branches covered by the campaign as it runs, overall and by the driver reporting them. The
+// log follows the coverage over time as the inputs tool writes it (one line per input): time
+// unit and time in ms, branches of every fuzzer in the header and of the whole campaign.
+pub struct CoverageMap {
+    global: HashSet<Branch>,
+    fuzzers: HashMap<String, HashSet<Branch>>,
+    // columns of the log, drivers joining later only count in the global one
+    log_fuzzer_ids: Vec<String>,
+    log_file: Option<File>,
+    log_filename: String
+}
+
+impl CoverageMap {
+    pub fn new() -> CoverageMap {
+        CoverageMap {
+            global: HashSet::new(),
+            fuzzers: HashMap::new(),
+            log_fuzzer_ids: vec![],
+            log_file: None,
+            log_filename: String::new()
+        }
+    }
+
+    // start the log over, a resumed campaign replays its inputs into it
+    pub fn open_log(&mut self, filename: &str, fuzzer_ids: Vec<String>) -> Result<(), String> {
+        let mut file = File::create(filename).map_err(|e| {
+            format!("failed to create {}: {}", filename, e)
+        })?;
+
+        let zeros = vec!["0"; fuzzer_ids.len() + 1].join(LOG_LINE_SEPARATOR);
+        let header = format!("unit{sep}time{sep}{}{sep}global\n0{sep}0{sep}{}\n",
+            fuzzer_ids.join(LOG_LINE_SEPARATOR), zeros, sep=LOG_LINE_SEPARATOR);
+        file.write_all(header.as_bytes()).map_err(|e| {
+            format!("failed writing header to {}: {}", filename, e)
+        })?;
+
+        self.log_fuzzer_ids = fuzzer_ids;
+        self.log_file = Some(file);
+        self.log_filename = filename.to_string();
+        Ok(())
+    }
+
+    // add the coverage of an input, returning whether the campaign covers more branches
+    pub fn add(&mut self, fuzzer_id: &str, branches: &[Branch], elapsed_time: Duration)
+        -> Result<bool, String>
+    {
+        let global_before = self.global.len();
+        self.global.extend(branches.iter().cloned());
+        self.fuzzers.entry(fuzzer_id.to_string()).or_default().extend(branches.iter().cloned());
+
+        if let Some(ref mut file) = self.log_file {
+            let fuzzers = &self.fuzzers;
+            let counts: Vec<_> = self.log_fuzzer_ids.iter().map(|f| {
+                fuzzers.get(f).map(|b| b.len()).unwrap_or(0).to_string()
+            }).collect();
+            let time = elapsed_time.num_milliseconds();
+            let line = format!("{}{sep}{}{sep}{}{sep}{}\n", time, time,
+                counts.join(LOG_LINE_SEPARATOR), self.global.len(), sep=LOG_LINE_SEPARATOR);
+            let log_filename = &self.log_filename;
+            file.write_all(line.as_bytes()).map_err(|e| {
+                format!("failed writing to {}: {}", log_filename, e)
+            })?;
+        }
+
+        Ok(self.global.len() > global_before)
+    }
+
+    pub fn global_len(&self) -> usize { self.global.len() }
+
+    pub fn fuzzer_len(&self, fuzzer_id: &str) -> usize {
+        self.fuzzers.get(fuzzer_id).map(|b| b.len()).unwrap_or(0)
+    }
+}
+
+
+// the same for coverage files with the same branches, in whatever order
+pub fn coverage_signature(coverage_path: &str) -> io::Result<u64> {
+    let branches: HashSet<Branch> = read_structs(coverage_path)?.into_iter().collect();
+    Ok(branches.iter().fold(0u64, |signature, branch| {
+        let mut hasher = FnvHasher::default();
+        branch.hash(&mut hasher);
+        signature.wrapping_add(hasher.finish())
+    }))
+}
@@ -0,0 +1,149 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+
+use nix::unistd::isatty;
+
+use time::{Duration, PreciseTime};
+
+
+const REDRAW_INTERVAL_MS: i64 = 1000;
+const RECENT_EVENTS: usize = 10;
+
+// ANSI sequences: alternate screen, cursor visibility, home and clear
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+
+// a fuzzer as the master sees it, gathered for each redraw
+pub struct FuzzerRow {
+    pub fuzzer_id: String,
+    pub liveness: String,
+    pub restarts: u32,
+    pub interesting: usize,
+    pub injected: usize,
+    pub branches: usize,
+    // inputs collected for a batched fuzzer
+    pub batched: Option<usize>
+}
+
+
+#[derive(Default)]
+struct MetricStats {
+    count: usize,
+    zeros: usize,
+    sum: f64,
+    max: f64
+}
+
+
+// the status screen the master redraws while the campaign runs, in place of one line per input,
+// when stdout is a terminal
+pub struct Dashboard {
+    enabled: bool,
+    last_draw: Option<PreciseTime>,
+    // metrics of the inputs evaluated for each fuzzer, and how many it won
+    metrics: HashMap<String, MetricStats>,
+    wins: HashMap<String, usize>,
+    events: VecDeque<String>
+}
+
+impl Dashboard {
+    // falls back to plain lines if not wanted or stdout is not a terminal
+    pub fn new(wanted: bool) -> Dashboard {
+        Dashboard {
+            enabled: wanted && isatty(1).unwrap_or(false),
+            last_draw: None,
+            metrics: HashMap::new(),
+            wins: HashMap::new(),
+            events: VecDeque::new()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool { self.enabled }
+
+    pub fn open(&mut self) {
+        if self.enabled {
+            print!("{}", ENTER_SCREEN);
+        }
+    }
+
+    // back to the terminal as it was, for the summary to stay in sight
+    pub fn close(&mut self) {
+        if self.enabled {
+            print!("{}", LEAVE_SCREEN);
+            let _ = io::stdout().flush();
+            self.enabled = false;
+        }
+    }
+
+    pub fn metric(&mut self, fuzzer_id: &str, metric: f64) {
+        let stats = self.metrics.entry(fuzzer_id.to_string()).or_default();
+        stats.max = if stats.count == 0 { metric } else { stats.max.max(metric) };
+        stats.count += 1;
+        stats.sum += metric;
+        if metric == 0.0 {
+            stats.zeros += 1;
+        }
+    }
+
+    pub fn won(&mut self, fuzzer_id: &str) {
+        *self.wins.entry(fuzzer_id.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn event(&mut self, elapsed_time: Duration, event: &str, fuzzer_id: &str, details: &str) {
+        if self.events.len() == RECENT_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(format!("{} {:<10} {:<16} {}", format_time(elapsed_time), event,
+            fuzzer_id, details));
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.enabled && self.last_draw.map(|d| {
+            d.to(PreciseTime::now()).num_milliseconds() >= REDRAW_INTERVAL_MS
+        }).unwrap_or(true)
+    }
+
+    pub fn draw(&mut self, elapsed_time: Duration, branches: usize, interesting: usize,
+        crashes: usize, rows: &[FuzzerRow])
+    {
+        self.last_draw = Some(PreciseTime::now());
+
+        let mut screen = String::from(CLEAR_SCREEN);
+        screen += &format!("{}uberfuzz{} - {} - {} branches - {} interesting - {} unique \
+            crashes\n\n", BOLD, RESET, format_time(elapsed_time), branches, interesting, crashes);
+
+        screen += &format!("{}{:<16} {:<14} {:>8} {:>8} {:>6} {:>8} {:>8} {:>10} {:>8} {:>5} \
+            {:>7}{}\n", BOLD, "fuzzer", "state", "restarts", "found", "won", "injected",
+            "branches", "metric avg", "max", "zero", "batched", RESET);
+        for row in rows {
+            let (average, max, zeros) = match self.metrics.get(&row.fuzzer_id) {
+                Some(stats) => (format!("{:.2}", stats.sum / stats.count as f64),
+                    format!("{:.2}", stats.max),
+                    format!("{}%", stats.zeros * 100 / stats.count)),
+                None => ("-".to_string(), "-".to_string(), "-".to_string())
+            };
+            screen += &format!("{:<16} {:<14} {:>8} {:>8} {:>6} {:>8} {:>8} {:>10} {:>8} {:>5} \
+                {:>7}\n", row.fuzzer_id, row.liveness, row.restarts, row.interesting,
+                self.wins.get(&row.fuzzer_id).unwrap_or(&0), row.injected, row.branches, average,
+                max, zeros, row.batched.map(|b| b.to_string()).unwrap_or("-".to_string()));
+        }
+
+        screen += &format!("\n{}recent events{}\n", BOLD, RESET);
+        for event in &self.events {
+            screen += event;
+            screen += "\n";
+        }
+
+        print!("{}", screen);
+        let _ = io::stdout().flush();
+    }
+}
+
+
+fn format_time(t: Duration) -> String {
+    format!("{:02}:{:02}:{:02}", t.num_hours(), t.num_minutes() % 60, t.num_seconds() % 60)
+}
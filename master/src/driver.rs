@@ -1,134 +1,192 @@
+use std::collections::HashMap;
 use std::convert::{From, Into};
-use std::fs::File;
-use std::str::FromStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::process::CommandExt;
 use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
 
-use master;
+use nix;
+use nix::errno::Errno;
+use nix::unistd::{self, Pid};
 
+use adapter::FuzzerAdapter;
+use transport::Transport;
 
-const DRIVER_EXE: &'static str = "./driver/driver";
-
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum FuzzerType {
-    AFL,
-    Honggfuzz,
-    VUzzer
-}
-
-impl FuzzerType {
-    fn get_inject_path(&self) -> &'static str {
-        match self {
-            &FuzzerType::AFL => "out/inject/queue",
-            &FuzzerType::Honggfuzz => "out/inject",
-            &FuzzerType::VUzzer => "special"
-        }
-    }
-}
-
-impl FromStr for FuzzerType {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "afl" => Ok(FuzzerType::AFL),
-            "hongg" => Ok(FuzzerType::Honggfuzz),
-            "vu" => Ok(FuzzerType::VUzzer),
-            _ => Err(format!("unable to parse {}", s))
-        }
-    }
-}
 
-impl ToString for FuzzerType {
-    fn to_string(&self) -> String {
-        match self {
-            &FuzzerType::AFL => "afl",
-            &FuzzerType::Honggfuzz => "hongg",
-            &FuzzerType::VUzzer => "vu"
-        }.to_string()
-    }
-}
+const DRIVER_EXE: &'static str = "./driver/driver";
 
 
 #[derive(Debug)]
 pub struct Driver {
     fuzzer_id: String,
-    fuzzer_type: FuzzerType,
+    adapter: Rc<dyn FuzzerAdapter>,
     section_name: Option<String>,
     fuzzer_cmd_filename: String,
     basic_block_script: Option<String>,
     fuzzer_corpus_path: String,
     fuzzer_log_filename: String,
     fuzzer_log_err_filename: String,
-    interesting_port: u32,
-    use_port: u32,
-    metric_port: u32,
+    interesting_endpoint: String,
+    use_endpoint: String,
+    metric_port: Option<u32>,
+    // where the master connects to the metric server, known from the registration of drivers
+    // running elsewhere and from the hello of local ones
+    metric_endpoint: Option<String>,
+    remote: bool,
+    transport: Transport,
     data_path: String,
     inject_path: String,
+    crash_path: String,
+    // prefix of the crash file names, for fuzzers naming them as told on their command line
+    crash_prefix: String,
+    stats_path: Option<String>,
+    readiness_path: Option<String>,
     sut: Vec<String>,
     sut_input_file: Option<String>,
+    inline_limit: Option<usize>,
+    work_path: String,
     log_filename: String
 }
 
 
 impl Driver {
-    pub fn with_defaults(fuzzer_id: String, fuzzer_type: FuzzerType, sut: Vec<String>,
-                         sut_input_file: Option<String>, metric_port: u32, work_path: String,
-                         basic_block_script: Option<String>, section_name: Option<String>)
-                         -> Driver
+    // without a metric_port the driver picks one and reports it in its hello
+    pub fn new<OS>(fuzzer_id: String, adapter: Rc<dyn FuzzerAdapter>, sut: Vec<String>,
+                   sut_input_file: Option<String>, metric_port: Option<u32>, work_path: String,
+                   basic_block_script: OS, section_name: OS)
+                   -> Driver
+                   where OS: Into<Option<String>>
     {
-        Driver::new(fuzzer_id, fuzzer_type, sut, sut_input_file, metric_port, work_path,
-            basic_block_script, section_name, None, None)
-    }
-
-    pub fn new<OS, OU>(fuzzer_id: String, fuzzer_type: FuzzerType, sut: Vec<String>,
-                       sut_input_file: Option<String>, metric_port: u32, work_path: String,
-                       basic_block_script: OS, section_name: OS, interesting_port: OU, use_port: OU)
-                       -> Driver
-                       where OS: Into<Option<String>>,
-                             OU: Into<Option<u32>>
-    {
-        let corpus_path = match fuzzer_type {
-            FuzzerType::AFL => format!("out/{}/queue", fuzzer_id),
-            FuzzerType::Honggfuzz => "in".to_string(),
-            FuzzerType::VUzzer => "special".to_string()
-        };
-
-        let inject_path = fuzzer_type.get_inject_path();
+        let fuzzer_path = format!("{}/{}", work_path, fuzzer_id);
 
         Driver {
             fuzzer_id: fuzzer_id.clone(),
-            fuzzer_type: fuzzer_type,
             section_name: section_name.into(),
-            fuzzer_cmd_filename: format!("{}/{}.{}.conf", work_path, fuzzer_id, fuzzer_type.to_string()),
+            fuzzer_cmd_filename: format!("{}/{}.{}.conf", work_path, fuzzer_id, adapter.name()),
             basic_block_script: basic_block_script.into(),
-            fuzzer_corpus_path: format!("{}/{}/{}", work_path, fuzzer_id, corpus_path),
+            fuzzer_corpus_path: format!("{}/{}", fuzzer_path, adapter.corpus_path(&fuzzer_id)),
             fuzzer_log_filename: format!("{}/{}.fuzz.log", work_path, fuzzer_id),
             fuzzer_log_err_filename: format!("{}/{}.fuzz.err.log", work_path, fuzzer_id),
-            interesting_port: interesting_port.into().unwrap_or(master::INTERESTING_PORT),
-            use_port: use_port.into().unwrap_or(master::USE_PORT),
+            interesting_endpoint: String::new(),
+            use_endpoint: String::new(),
             metric_port: metric_port,
+            metric_endpoint: None,
+            remote: false,
+            transport: Transport::Tcp,
             data_path: format!("{}/{}/driver", work_path, fuzzer_id),
-            inject_path: format!("{}/{}/{}", work_path, fuzzer_id, inject_path),
+            inject_path: format!("{}/{}", fuzzer_path, adapter.inject_path(&fuzzer_id)),
+            crash_path: format!("{}/{}", fuzzer_path, adapter.crash_path(&fuzzer_id)),
+            crash_prefix: String::new(),
+            stats_path: adapter.stats_path(&fuzzer_id).map(|p| format!("{}/{}", fuzzer_path, p)),
+            readiness_path: adapter.readiness_path(&fuzzer_id)
+                .map(|p| format!("{}/{}", fuzzer_path, p)),
             sut: sut,
             sut_input_file: sut_input_file,
-            log_filename: format!("{}/{}.log", work_path, fuzzer_id)
+            inline_limit: None,
+            log_filename: format!("{}/{}.log", work_path, fuzzer_id),
+            work_path,
+            adapter
+        }
+    }
+
+    // a driver started elsewhere (registered or described in the campaign file), reached at its
+    // metric endpoint and never spawned by the master
+    pub fn new_remote(fuzzer_id: String, adapter: Rc<dyn FuzzerAdapter>, metric_endpoint: String)
+        -> Driver
+    {
+        let mut driver = Driver::new(fuzzer_id, adapter, vec![], None, None, String::new(),
+            None::<String>, None::<String>);
+        driver.metric_endpoint = Some(metric_endpoint);
+        driver.remote = true;
+        driver
+    }
+
+    pub fn spawn(&self) -> Result<Child, String> {
+        let file = File::create(&self.log_filename).map_err(|e| {
+            format!("failed to create {}: {}", self.log_filename, e)
+        })?;
+
+        self.spawn_logging_to(file, &self.fuzzer_cmd_filename, false)
+    }
+
+    // spawn again after a failure or when resuming a campaign, so that the driver and its fuzzer
+    // continue from the state they left behind, appending to the existing log
+    pub fn respawn(&self) -> Result<Child, String> {
+        let file = OpenOptions::new().create(true).append(true).open(&self.log_filename)
+            .map_err(|e| format!("failed to open {}: {}", self.log_filename, e))?;
+
+        let fuzzer_cmd_filename = self.resume_cmd_filename()?;
+        self.spawn_logging_to(file, &fuzzer_cmd_filename, true)
+    }
+
+    // use the paths the command line of the fuzzer sets, if its type knows where to look
+    pub fn locate_paths(&mut self) -> Result<(), String> {
+        let layout = self.adapter.command_layout(&self.read_command()?);
+        if let Some(corpus_path) = layout.corpus {
+            self.fuzzer_corpus_path = corpus_path;
+        }
+        if let Some(inject_path) = layout.inject {
+            self.inject_path = inject_path;
         }
+        if let Some(crash_path) = layout.crashes {
+            self.crash_path = crash_path;
+        }
+        if let Some(crash_prefix) = layout.crash_prefix {
+            self.crash_prefix = crash_prefix;
+        }
+        if layout.stats.is_some() {
+            self.stats_path = layout.stats;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_command(&self) -> Result<Vec<String>, String> {
+        let mut contents = String::new();
+        File::open(&self.fuzzer_cmd_filename).and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| format!("failed to read {}: {}", self.fuzzer_cmd_filename, e))?;
+
+        Ok(contents.lines().map(|l| l.to_string()).collect())
+    }
+
+    // some fuzzers need a different command line to continue from the state they left behind
+    fn resume_cmd_filename(&self) -> Result<String, String> {
+        let command = self.read_command()?;
+        let resume_command = match self.adapter.resume_command(&command) {
+            Some(resume_command) => resume_command,
+            None => return Ok(self.fuzzer_cmd_filename.clone())
+        };
+        let resume_contents = resume_command.iter().map(|s| format!("{}\n", s))
+            .collect::<String>();
+
+        let resume_filename = format!("{}/.{}.resume.conf", self.work_path, self.fuzzer_id);
+        File::create(&resume_filename)
+            .and_then(|mut f| f.write_all(resume_contents.as_bytes()))
+            .map_err(|e| format!("failed to write {}: {}", resume_filename, e))?;
+
+        Ok(resume_filename)
     }
 
-    pub fn spawn(&self) -> Child {
-        let ports = format!("{},{},{}", self.interesting_port, self.use_port, self.metric_port);
-        let file: File = File::create(&self.log_filename)
-            .expect(&format!("failed to create {}", self.log_filename));
+    fn spawn_logging_to(&self, file: File, fuzzer_cmd_filename: &str, resume: bool)
+        -> Result<Child, String>
+    {
+        let metric_endpoint = self.transport.bind_endpoint(&format!("{}.metric", self.fuzzer_id),
+            self.metric_port);
+        let endpoints = format!("{},{},{}", self.interesting_endpoint, self.use_endpoint,
+            metric_endpoint);
+        let inline_limit = self.inline_limit.map(|l| l.to_string());
 
         let mut args = vec![
             "-i", &self.fuzzer_id,
-            "-f", &self.fuzzer_cmd_filename,
+            "-f", fuzzer_cmd_filename,
             "-c", &self.fuzzer_corpus_path,
             "-l", &self.fuzzer_log_filename,
             "-L", &self.fuzzer_log_err_filename,
-            "-p", &ports,
+            "-E", &endpoints,
             "-d", &self.data_path,
-            "-j", &self.inject_path
+            "-j", &self.inject_path,
+            "-W", &self.work_path
         ];
 
         if let Some(ref basic_block_script) = self.basic_block_script {
@@ -143,18 +201,123 @@ impl Driver {
             args.extend_from_slice(&["-F", sut_input_file]);
         }
 
+        if let Some(ref inline_limit) = inline_limit {
+            args.extend_from_slice(&["-I", inline_limit]);
+        }
+
+        if let Some(ref readiness_path) = self.readiness_path {
+            args.extend_from_slice(&["-g", readiness_path]);
+        }
+
+        if resume {
+            args.push("-r");
+        }
+
         args.push("--");
         args.extend_from_slice(&self.sut.iter().map(|s| s.as_ref()).collect::<Vec<_>>());
 
-        Command::new(DRIVER_EXE)
-            .args(&args)
-            .stdout(Stdio::from(file))
-            // FIXME: .stderr(Stdio::from(file))
-            .spawn()
-            .expect(&format!("failed to spawn driver {}", self.fuzzer_id))
+        let mut command = Command::new(DRIVER_EXE);
+        // FIXME: .stderr(Stdio::from(file))
+        command.args(&args).stdout(Stdio::from(file));
+
+        // own process group, so that a ctrl-c in the terminal of the master only reaches the
+        // driver through the master, once it shuts the campaign down
+        unsafe {
+            command.pre_exec(|| {
+                unistd::setpgid(Pid::from_raw(0), Pid::from_raw(0))
+                    .map_err(|_| io::Error::last_os_error())
+            });
+        }
+        let child = command.spawn()
+            .map_err(|e| format!("failed to spawn driver {}: {}", self.fuzzer_id, e))?;
+
+        // and from here too, as the master may signal the group before the driver sets it up
+        // (an exec'd or exited driver has set it up already or has none left)
+        let pid = Pid::from_raw(child.id() as i32);
+        match unistd::setpgid(pid, pid) {
+            Ok(()) | Err(nix::Error::Sys(Errno::EACCES)) | Err(nix::Error::Sys(Errno::ESRCH)) => (),
+            Err(e) => warn!("failed to set the process group of driver {}: {}", self.fuzzer_id, e)
+        }
+
+        Ok(child)
+    }
+
+    // replace the corpus and inject paths derived from the fuzzer type
+    pub fn override_paths(&mut self, corpus_path: Option<String>, inject_path: Option<String>) {
+        if let Some(corpus_path) = corpus_path {
+            self.fuzzer_corpus_path = corpus_path;
+        }
+        if let Some(inject_path) = inject_path {
+            self.inject_path = inject_path;
+        }
+    }
+
+    pub fn set_data_path(&mut self, data_path: String) {
+        self.data_path = data_path;
+    }
+
+    pub fn set_fuzzer_cmd_filename(&mut self, filename: String) {
+        self.fuzzer_cmd_filename = filename;
+    }
+
+    // send inputs and coverage inside messages (up to `limit` bytes) instead of their paths
+    pub fn set_inline_limit(&mut self, limit: Option<usize>) {
+        self.inline_limit = limit;
+    }
+
+    pub fn set_transport(&mut self, transport: Transport) {
+        self.transport = transport;
+    }
+
+    // endpoints of the master, known once its sockets are bound
+    pub fn set_master_endpoints(&mut self, interesting_endpoint: String, use_endpoint: String) {
+        self.interesting_endpoint = interesting_endpoint;
+        self.use_endpoint = use_endpoint;
+    }
+
+    pub fn set_metric_endpoint(&mut self, metric_endpoint: String) {
+        self.metric_endpoint = Some(metric_endpoint);
+    }
+
+    pub fn get_metric_endpoint(&self) -> Option<&str> {
+        self.metric_endpoint.as_deref()
+    }
+
+    pub fn is_remote(&self) -> bool { self.remote }
+
+    pub fn get_crash_path(&self) -> &str { &self.crash_path }
+
+    pub fn is_crash_file(&self, filename: &str) -> bool {
+        filename.starts_with(&self.crash_prefix) &&
+            self.adapter.is_crash_file(&filename[self.crash_prefix.len()..])
+    }
+
+    pub fn get_readiness_path(&self) -> Option<&str> {
+        self.readiness_path.as_deref()
     }
 
-    pub fn get_metric_port(&self) -> u32 { self.metric_port }
+    pub fn is_batched(&self) -> bool { self.readiness_path.is_some() }
+
+    // a readiness file relative to the fuzzer directory, without one inputs are streamed
+    pub fn set_readiness_path(&mut self, readiness_path: Option<&str>) {
+        self.readiness_path = readiness_path.map(|p| {
+            format!("{}/{}/{}", self.work_path, self.fuzzer_id, p)
+        });
+    }
 
-    pub fn is_vuzzer(&self) -> bool { self.fuzzer_type == FuzzerType::VUzzer }
+    // statistics of the fuzzer, empty if it keeps none or they cannot be read
+    pub fn read_stats(&self) -> HashMap<String, String> {
+        let stats_path = match self.stats_path {
+            Some(ref stats_path) => stats_path,
+            None => return HashMap::new()
+        };
+
+        let mut contents = String::new();
+        if let Err(e) = File::open(stats_path).and_then(|mut f| f.read_to_string(&mut contents)) {
+            debug!("failed to read stats of {} from {}: {}", self.fuzzer_id, stats_path, e);
+            return HashMap::new();
+        }
+
+        self.adapter.parse_stats(&contents)
+    }
 }
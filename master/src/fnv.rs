@@ -0,0 +1,29 @@
+use std::hash::Hasher;
+
+
+// FNV-1a, the hash the driver names received blobs after. Unlike the standard hasher it does not
+// change across Rust releases, so blobs stored and signatures rebuilt by a resumed campaign match
+// the ones of the interrupted run.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher { FnvHasher(0xcbf29ce484222325) }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 { self.0 }
+}
+
+
+pub fn hash_bytes(data: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(data);
+    hasher.finish()
+}
@@ -7,11 +7,27 @@ extern crate getopts;
 extern crate time;
 extern crate rand;
 extern crate inotify;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate toml;
 
+mod adapter;
+mod batch;
+mod blobs;
 mod common;
+mod config;
+#[path = "bin/coverage/mod.rs"]
+mod coverage;
+mod coverage_map;
+mod dashboard;
 mod driver;
+mod fnv;
 mod master;
 mod messages;
+mod metric;
+mod stop;
+mod strategy;
+mod transport;
 
 use master::Master;
 
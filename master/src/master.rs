@@ -1,10 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::{self, File};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
 use std::io::prelude::Write;
+use std::mem;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::process::Child;
+use std::rc::Rc;
+use std::slice;
+use std::str::FromStr;
+use std::path::Path;
 use std::thread;
 use std::time;
 
@@ -18,28 +25,37 @@ use getopts::Options;
 
 use time::{Duration, PreciseTime};
 
-use rand::{Rng, thread_rng};
-
 use inotify::{WatchMask, Inotify, WatchDescriptor};
 
-use driver::{Driver, FuzzerType};
-use messages::{InterestingInput, ReqMetric, RepMetric};
+use adapter::{Adapters, FuzzerAdapter};
+use batch::{Batch, DEFAULT_BATCH_SIZE};
+use blobs::BlobStore;
+use config::{CampaignConfig, FuzzerConfig, find_conf_files, discover_fuzzer, parse_duration};
+use coverage::{Branch, read_structs};
+use coverage_map::{CoverageMap, coverage_signature};
+use dashboard::{Dashboard, FuzzerRow};
+use driver::Driver;
+use metric::{self, CoverageTable, Metrics};
+use messages::{self, DriverMessage, Hello, InputData, InterestingInput, Ready, Registration,
+    RegistrationMessage, ReqMetric, RepMetric};
+use stop::{CampaignProgress, CrashCounter, StopConditions};
+use strategy::{self, CampaignState, WinningStrategy};
+use transport::Transport;
 use common::{LOG_LINE_SEPARATOR, WORK_PATH};
 
 
-pub const INTERESTING_PORT: u32 = 1337;
-pub const USE_PORT: u32 = INTERESTING_PORT + 1;
-pub const METRIC_PORT_START: u32 = USE_PORT + 1;
-const BIND_ADDR: &'static str = "tcp://*";
-const CONN_ADDR: &'static str = "tcp://localhost";
 const DEFAULT_BB_SCRIPT: &'static str = "./r2.sh -b";
 const DEFAULT_SECTION: &'static str = ".text";
-
-
-enum WinningStrategy {
-    SingleWinner(bool),
-    MultipleWinners(f64, bool)
-}
+const STRATEGY_DUMP_INTERVAL_SECS: i64 = 10;
+const DEFAULT_METRIC_TIMEOUT_MS: i64 = 5000;
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const RESTART_BACKOFF_START_SECS: i64 = 1;
+const RESTART_BACKOFF_MAX_SECS: i64 = 60;
+const DEFAULT_GRACE_PERIOD_SECS: i64 = 10;
+const CRASH_SCAN_INTERVAL_SECS: i64 = 10;
+const REMOTE_MAX_MISSED_METRICS: u32 = 3;
+// between the receivers of an input in winning.log
+const WINNERS_SEPARATOR: &str = ",";
 
 
 struct InterestingWithTime {
@@ -47,41 +63,256 @@ struct InterestingWithTime {
     elapsed_time: Duration
 }
 
-impl ToString for InterestingWithTime {
-    fn to_string(&self) -> String {
-        format!("{}{sep}{}{sep}{}{sep}{}", self.elapsed_time.num_milliseconds(),
+impl fmt::Display for InterestingWithTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{sep}{}{sep}{}{sep}{}", self.elapsed_time.num_milliseconds(),
             self.input_message.fuzzer_id, self.input_message.input_path,
             self.input_message.coverage_path, sep=LOG_LINE_SEPARATOR)
     }
 }
 
+impl FromStr for InterestingWithTime {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(LOG_LINE_SEPARATOR).collect();
+        if fields.len() != 4 {
+            return Err(format!("unable to parse input from '{}'", s));
+        }
+
+        let input_message = InterestingInput {
+            fuzzer_id: fields[1].to_string(),
+            input_path: fields[2].to_string(),
+            coverage_path: fields[3].to_string(),
+            data: None
+        };
+
+        Ok(InterestingWithTime {
+            input_message,
+            elapsed_time: parse_log_time(fields[0])?
+        })
+    }
+}
+
+
+// the elapsed time (in ms) a log line starts with
+fn parse_log_time(line: &str) -> Result<Duration, String> {
+    let ms_str = line.split(LOG_LINE_SEPARATOR).next().unwrap_or("");
+    let ms = ms_str.parse().map_err(|e| {
+        format!("unable to parse time from '{}': {}", line, e)
+    })?;
+
+    Ok(Duration::milliseconds(ms))
+}
+
+
+// the lines of a log left by a previous run, if any
+fn read_log_lines(filename: &str) -> Result<Vec<String>, String> {
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to open {}: {}", filename, e))
+    };
+
+    let lines: Result<Vec<_>, _> = BufReader::new(file).lines().collect();
+    lines.map(|l| l.into_iter().filter(|l| !l.is_empty()).collect())
+        .map_err(|e| format!("failed to read {}: {}", filename, e))
+}
+
+
+// logs of an interrupted run are appended to when resuming it
+fn open_log(filename: &str, append: bool) -> Result<File, String> {
+    let file_res = if append {
+        OpenOptions::new().create(true).append(true).open(filename)
+    } else {
+        File::create(filename)
+    };
+
+    file_res.map_err(|e| format!("failed to open {}: {}", filename, e))
+}
+
+
+// a command line in place of an id.type.conf file
+fn write_command(filename: &str, command: &[String]) -> Result<(), String> {
+    let contents = command.iter().map(|s| format!("{}\n", s)).collect::<String>();
+    File::create(filename).and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(|e| format!("failed to write {}: {}", filename, e))
+}
+
+// a driver spawned by the master, running the target on its own input file
+fn local_driver(config: &CampaignConfig, fuzzer_id: &str, adapter: Rc<dyn FuzzerAdapter>,
+    metric_port: Option<u32>, transport: &Transport) -> Driver
+{
+    let wp = config.get_work_path();
+
+    // set sut input filename (if used)
+    let sut_input_file = if config.stdin { None }
+        else { Some(format!("{}/.{}.input", wp, fuzzer_id)) };
+
+    // set input filename (if any) in sut arguments, replacing any '@@' occurrence
+    let sut = config.sut.iter().map(|s| {
+        if s == "@@" { sut_input_file.clone().unwrap_or(s.to_string()) }
+        else { s.to_string() }
+    }).collect();
+
+    let mut driver = Driver::new(fuzzer_id.to_string(), adapter, sut, sut_input_file, metric_port,
+        wp, config.basic_blocks.clone(), config.section.clone());
+    driver.set_inline_limit(config.inline);
+    driver.set_transport(transport.clone());
+    driver
+}
+
+
+// size of an input, unknown (0) if its file is not reachable
+fn input_size(interesting_input: &InterestingInput) -> u64 {
+    fs::metadata(&interesting_input.input_path).map(|m| m.len()).unwrap_or(0)
+}
+
+
+// the next metric port handed out from the starting one, skipping the ones set explicitly
+fn next_metric_port(next_port: &mut Option<u32>, explicit_ports: &HashSet<u32>) -> Option<u32> {
+    while let Some(port) = *next_port {
+        *next_port = Some(port + 1);
+        if !explicit_ports.contains(&port) {
+            return Some(port);
+        }
+    }
+    None
+}
+
+
+// fuzzer ids are fields of the space separated logs, and listed in winning.log
+fn check_fuzzer_id(fuzzer_id: &str) -> Result<(), String> {
+    if fuzzer_id.is_empty() ||
+        fuzzer_id.contains(|c: char| c.is_whitespace() || WINNERS_SEPARATOR.contains(c))
+    {
+        return Err(format!("invalid fuzzer id '{}', ids cannot contain spaces or '{}'",
+            fuzzer_id, WINNERS_SEPARATOR));
+    }
+    Ok(())
+}
+
+
+// the coverage signature of an input, unknown if its coverage file is not readable
+fn input_signature(interesting_input: &InterestingInput) -> Option<u64> {
+    match coverage_signature(&interesting_input.coverage_path) {
+        Ok(signature) => Some(signature),
+        Err(e) => {
+            debug!("no coverage signature for {}: {}", interesting_input.input_path, e);
+            None
+        }
+    }
+}
+
+
+// fuzzers with a readiness file are batched unless the campaign file tells to stream inputs to
+// them, as they are found
+fn batch_for(fuzzer: &FuzzerConfig, driver: &mut Driver, high: bool)
+    -> Result<Option<Batch>, String>
+{
+    if let Some(ref readiness) = fuzzer.readiness {
+        driver.set_readiness_path(Some(readiness));
+    }
+
+    match fuzzer.delivery.as_deref() {
+        Some("streaming") => driver.set_readiness_path(None),
+        Some("batched") if !driver.is_batched() => {
+            return Err(format!("batched fuzzer {} needs a readiness file", fuzzer.id));
+        },
+        Some("batched") | None => (),
+        Some(delivery) => {
+            return Err(format!("unknown delivery {} for fuzzer {} (streaming or batched)",
+                delivery, fuzzer.id));
+        }
+    }
+
+    if !driver.is_batched() {
+        if fuzzer.batch_size.is_some() || fuzzer.batch_max_age.is_some() {
+            return Err(format!("fuzzer {} is not batched but has batch settings", fuzzer.id));
+        }
+        return Ok(None);
+    }
+
+    let size = fuzzer.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    if size == 0 {
+        return Err(format!("batch size of fuzzer {} must be at least 1", fuzzer.id));
+    }
+    let max_age = match fuzzer.batch_max_age {
+        Some(ref max_age) => Some(parse_duration(max_age)?),
+        None => None
+    };
+
+    Ok(Some(Batch::new(size, max_age, high)))
+}
+
+
+struct DriverSupervision {
+    failures: u32,
+    restart_at: Option<Duration>
+}
 
-struct WatchDescriptorData {
+
+// the readiness file of a batched fuzzer, watched in case its driver sends no ready messages
+struct ReadinessWatch {
     fuzzer_id: String,
-    modified: usize,
-    ready: bool
+    file_name: String
 }
 
 
 pub struct Master {
     sut: Vec<String>,
-    winning_strategy: WinningStrategy,
+    winning_strategy: Box<dyn WinningStrategy>,
+    adapters: Adapters,
     drivers: HashMap<String, Driver>,
+    // secondary instances of a fuzzer, by their driver id, take part in the campaign as the
+    // fuzzer they belong to
+    group_leaders: HashMap<String, String>,
+    batches: HashMap<String, Batch>,
     processes: HashMap<String, Child>,
+    supervision: HashMap<String, DriverSupervision>,
+    max_restarts: u32,
+    grace_period: Duration,
+    stop_conditions: StopConditions,
+    crash_counter: CrashCounter,
+    coverage_map: CoverageMap,
+    // computing metrics on the master instead of asking drivers
+    local_coverage: Option<CoverageTable>,
+    metrics: Metrics,
+    // coverage signatures of the inputs each fuzzer found or was given, inputs with the same
+    // coverage are not evaluated for it again
+    known_signatures: HashMap<String, HashSet<u64>>,
+    // inputs not evaluated for each fuzzer as it had one with the same coverage already
+    duplicates: HashMap<String, usize>,
+    last_interesting: Duration,
+    last_coverage_increase: Duration,
+    won_counts: HashMap<String, usize>,
+    // ports not given are picked by the system when binding
+    interesting_port: Option<u32>,
+    use_port: Option<u32>,
+    registration_port: Option<u32>,
+    transport: Transport,
+    context: zmq::Context,
     interesting_pull: Option<zmq::Socket>,
     use_pub: Option<zmq::Socket>,
+    registration_rep: Option<zmq::Socket>,
     metric_reqs: HashMap<String, zmq::Socket>,
+    // consecutive metric requests remote drivers did not reply to
+    remote_missed: HashMap<String, u32>,
+    metric_timeout: Duration,
+    blob_store: Option<BlobStore>,
+    resume: bool,
+    time_offset: Duration,
     start_time: Option<PreciseTime>,
     work_path: String,
     interesting_log: Vec<InterestingWithTime>,
     interesting_log_file: Option<File>,
-    winning_log_file: Option<File>
+    winning_log_file: Option<File>,
+    strategy_log_file: Option<File>,
+    dedup_log_file: Option<File>,
+    dashboard: Dashboard,
+    events_log_file: Option<File>
 }
 
 
-type BestInterestingMap = HashMap<String, Vec<(InterestingInput, RepMetric)>>;
-
-
 impl Master {
     fn usage(program: &str, opts: Options) -> String {
         let brief = format!("usage: {} [options] -- target [args]", program);
@@ -94,127 +325,435 @@ impl Master {
 
         let mut opts = Options::new();
         opts.optflag("h", "help", "Print this help");
+        opts.optopt("c", "campaign", "Campaign file (flags override its settings)",
+            "campaign.toml");
+        opts.optopt("W", "work-dir", "Work directory", WORK_PATH);
         opts.optmulti("f", "fuzzer", "Fuzzer id (from id.type.conf in work directory)", "aflfast");
         opts.optflag("H", "high", "High or low winning strategy");
         opts.optopt("t", "winning-threshold", "Winning strategy threshold", "0.42");
+        opts.optopt("k", "top-k", "The k drivers with the best metric win", "2");
+        opts.optopt("P", "proportional",
+            "Sample winners with probability proportional to their metric", "1");
+        opts.optopt("U", "ucb",
+            "UCB1 bandit rewarding drivers reporting inputs within a window (ms) from injection",
+            "60000");
+        opts.optopt("T", "metric-timeout", "Time (ms) to wait for a driver's metric",
+            "5000");
+        opts.optopt("R", "max-restarts", "Times drivers are restarted before giving up", "5");
+        opts.optopt("g", "grace-period", "Seconds drivers have to exit cleanly on shutdown", "10");
+        opts.optopt("", "registration", "Let drivers started elsewhere join through this port",
+            "1336");
+        opts.optopt("", "time-budget", "Stop the campaign after this long", "24h");
+        opts.optopt("", "max-idle", "Stop when no interesting input is found for this long",
+            "2h");
+        opts.optopt("", "coverage-plateau", "Stop when coverage does not increase for this long",
+            "4h");
+        opts.optopt("", "target-crashes", "Stop after finding this many unique crashes", "10");
+        opts.optopt("I", "inline",
+            "Send inputs and coverage inside messages, up to this many bytes", "1048576");
+        opts.optopt("", "transport", "How the master talks to the drivers it spawns (tcp or ipc)",
+            "ipc");
+        opts.optmulti("", "metric", "Metric inputs are evaluated with (weighted by the campaign \
+            file)", "new-branches");
+        opts.optflag("", "local-metric",
+            "Compute metrics on the master from coverage files instead of asking drivers");
+        opts.optflag("", "plain", "Print a line per input instead of the status screen");
+        opts.optflag("r", "resume", "Resume the interrupted campaign in the work directory");
         opts.optflag("s", "stdin", "Target reads from standard input");
         opts.optflag("B", "basic-blocks", "Drivers use basic blocks from static analysis");
         opts.optflag("S", "section", "Drivers use only the .text section of the target");
 
         let matches = opts.parse(&args[1..]).map_err(|f| f.to_string())?;
 
-        if matches.opt_present("h") || matches.free.is_empty() || matches.opt_count("f") < 2 {
+        // with registration enabled, drivers can join the campaign later
+        let joinable = matches.opt_present("registration");
+        if matches.opt_present("h") || (!matches.opt_present("c") &&
+            (matches.free.is_empty() || (matches.opt_count("f") < 2 && !joinable)))
+        {
             return Err(Master::usage(&program, opts));
         }
 
-        // collect .conf files from WORK_PATH
-        let work_readdir = fs::read_dir(WORK_PATH).map_err(|e| {
-            format!("failed to read directory {}: {}", WORK_PATH, e)
-        })?;
+        let mut config = match matches.opt_str("c") {
+            Some(campaign_filename) => CampaignConfig::from_file(&campaign_filename)?,
+            None => CampaignConfig::default()
+        };
 
-        let mut conf_files = vec![];
-        for entry in work_readdir {
-            let path = entry.map_err(|e| e.to_string())?.path();
+        // command line flags override the campaign file
+        if let Some(work_dir) = matches.opt_str("W") {
+            config.work_dir = Some(work_dir);
+        }
 
-            let valid_file = {
-                let ext_opt = path.extension();
-                path.is_file() && ext_opt.is_some() && ext_opt.unwrap() == "conf"
-            };
+        if !matches.free.is_empty() {
+            config.sut = matches.free.clone();
+        }
 
-            if valid_file {
-                conf_files.push(path);
-            }
+        if matches.opt_present("s") {
+            config.stdin = true;
         }
 
-        debug!("found conf files: {:?}", conf_files);
+        // metrics chosen on the command line replace the ones in the campaign file
+        if matches.opt_present("metric") {
+            config.metrics = matches.opt_strs("metric");
+            config.metric_weights = None;
+        }
 
-        let bb_script = if matches.opt_present("B") {
-            Some(DEFAULT_BB_SCRIPT.to_string())
-        } else { None };
+        if matches.opt_present("local-metric") {
+            config.local_metric = true;
+        }
 
-        let section_name = if matches.opt_present("S") {
-            Some(DEFAULT_SECTION.to_string())
-        } else { None };
+        if matches.opt_present("plain") {
+            config.plain = true;
+        }
 
-        let mut drivers_map = HashMap::new();
-        let mut metric_port = METRIC_PORT_START;
-        for fuzzer_id in matches.opt_strs("f") {
-            // find conf file starting with this fuzzer id
-            let conf_path_opt = conf_files.iter().find(|p| {
-                p.to_str().unwrap().contains(&fuzzer_id)
-            });
+        if matches.opt_present("r") {
+            config.resume = true;
+        }
+
+        if let Some(limit_str) = matches.opt_str("I") {
+            let limit = limit_str.parse().map_err(|e| {
+                format!("unable to parse {} as inline limit: {}", limit_str, e)
+            })?;
+            config.inline = Some(limit);
+        }
+
+        if let Some(transport) = matches.opt_str("transport") {
+            config.transport = Some(transport);
+        }
+
+        if let Some(timeout_str) = matches.opt_str("T") {
+            let timeout = timeout_str.parse().map_err(|e| {
+                format!("unable to parse {} as metric timeout: {}", timeout_str, e)
+            })?;
+            config.metric_timeout = Some(timeout);
+        }
+
+        if let Some(restarts_str) = matches.opt_str("R") {
+            let restarts = restarts_str.parse().map_err(|e| {
+                format!("unable to parse {} as max restarts: {}", restarts_str, e)
+            })?;
+            config.max_restarts = Some(restarts);
+        }
+
+        if let Some(grace_str) = matches.opt_str("g") {
+            let grace = grace_str.parse().map_err(|e| {
+                format!("unable to parse {} as grace period: {}", grace_str, e)
+            })?;
+            config.grace_period = Some(grace);
+        }
+
+        if let Some(port_str) = matches.opt_str("registration") {
+            let port = port_str.parse().map_err(|e| {
+                format!("unable to parse {} as registration port: {}", port_str, e)
+            })?;
+            config.ports.registration = Some(port);
+        }
+
+        if let Some(budget) = matches.opt_str("time-budget") {
+            config.stop.time_budget = Some(budget);
+        }
+
+        if let Some(max_idle) = matches.opt_str("max-idle") {
+            config.stop.max_idle = Some(max_idle);
+        }
+
+        if let Some(plateau) = matches.opt_str("coverage-plateau") {
+            config.stop.coverage_plateau = Some(plateau);
+        }
+
+        if let Some(crashes_str) = matches.opt_str("target-crashes") {
+            let crashes = crashes_str.parse().map_err(|e| {
+                format!("unable to parse {} as target crashes: {}", crashes_str, e)
+            })?;
+            config.stop.crashes = Some(crashes);
+        }
+
+        if matches.opt_present("B") {
+            config.basic_blocks = Some(DEFAULT_BB_SCRIPT.to_string());
+        }
+
+        if matches.opt_present("S") {
+            config.section = Some(DEFAULT_SECTION.to_string());
+        }
+
+        if matches.opt_present("H") {
+            config.winning.high = true;
+        }
+
+        // a strategy chosen on the command line replaces the one in the campaign file
+        if matches.opts_present(&["t".to_string(), "k".to_string(), "P".to_string(),
+            "U".to_string()])
+        {
+            config.winning.threshold = None;
+            config.winning.top_k = None;
+            config.winning.proportional = None;
+            config.winning.ucb_window = None;
+        }
+
+        if let Some(threshold_str) = matches.opt_str("t") {
+            let threshold = threshold_str.parse().map_err(|e| {
+                format!("unable to parse {} as threshold: {}", threshold_str, e)
+            })?;
+            config.winning.threshold = Some(threshold);
+        }
+
+        if let Some(k_str) = matches.opt_str("k") {
+            let k = k_str.parse().map_err(|e| {
+                format!("unable to parse {} as top-k: {}", k_str, e)
+            })?;
+            config.winning.top_k = Some(k);
+        }
+
+        if let Some(n_str) = matches.opt_str("P") {
+            let n = n_str.parse().map_err(|e| {
+                format!("unable to parse {} as number of proportional winners: {}", n_str, e)
+            })?;
+            config.winning.proportional = Some(n);
+        }
+
+        if let Some(window_str) = matches.opt_str("U") {
+            let window = window_str.parse().map_err(|e| {
+                format!("unable to parse {} as ucb window: {}", window_str, e)
+            })?;
+            config.winning.ucb_window = Some(window);
+        }
 
-            if conf_path_opt.is_none() {
-                let e = format!("a config file for {} was not found in {}", fuzzer_id, WORK_PATH);
-                return Err(e);
+        // fuzzers given with -f replace the ones in the campaign file, the ones not described
+        // there are looked up in the work directory
+        if matches.opt_present("f") {
+            let work_path = config.get_work_path();
+            let conf_files = find_conf_files(&work_path)?;
+            let mut fuzzers = vec![];
+            for fuzzer_id in matches.opt_strs("f") {
+                let fuzzer = match config.find_fuzzer(&fuzzer_id) {
+                    Some(fuzzer) => fuzzer.clone(),
+                    None => discover_fuzzer(&fuzzer_id, &conf_files, &work_path)?
+                };
+                fuzzers.push(fuzzer);
             }
+            config.fuzzers = fuzzers;
+        }
+
+        if config.sut.is_empty() ||
+            (config.fuzzers.len() < 2 && config.ports.registration.is_none())
+        {
+            return Err(Master::usage(&program, opts));
+        }
 
-            let conf_path = conf_path_opt.unwrap();
-            // parse fuzzer type from conf filename
-            let conf_filename = conf_path.file_name().unwrap().to_string_lossy();
-            let conf_filename_split: Vec<_> = conf_filename.split(".").collect();
-            if conf_filename_split.len() < 3 {
-                return Err(format!("invalid conf filename {}", conf_path.display()));
+        Master::from_config(config)
+    }
+
+    pub fn from_config(config: CampaignConfig) -> Result<Master, String> {
+        let wp = config.get_work_path();
+        let transport = Transport::from_config(config.transport.as_deref(),
+            &wp)?;
+        let has_remote = config.ports.registration.is_some() ||
+            config.fuzzers.iter().any(|f| f.endpoint.is_some());
+        if has_remote && !transport.is_tcp() {
+            return Err("drivers running elsewhere need the tcp transport".to_string());
+        }
+        // inputs and coverage files of drivers running elsewhere are not reachable otherwise
+        if has_remote && config.inline.is_none() {
+            return Err("drivers running elsewhere need inline mode".to_string());
+        }
+
+        let winning_strategy = strategy::from_config(&config.winning)?;
+        let high = winning_strategy.is_high();
+        let metrics = Metrics::from_config(&config.metrics, config.metric_weights.as_ref(),
+            config.local_metric)?;
+
+        let mut drivers_map = HashMap::new();
+        let mut group_leaders = HashMap::new();
+        let mut batches = HashMap::new();
+        // without a starting port drivers pick their metric port and report it in their hello
+        let mut metric_port = config.ports.metric_start;
+        let mut explicit_ports = HashSet::new();
+        for port in config.fuzzers.iter().filter_map(|f| f.metric_port) {
+            if !explicit_ports.insert(port) {
+                return Err(format!("metric port {} is set for more than one fuzzer", port));
             }
+        }
+        let adapters = Adapters::from_config(&config.adapters)?;
+        for fuzzer in &config.fuzzers {
+            let fuzzer_id = fuzzer.id.clone();
+            if drivers_map.contains_key(&fuzzer_id) {
+                return Err(format!("fuzzer {} is defined more than once", fuzzer_id));
+            }
+            check_fuzzer_id(&fuzzer_id)?;
 
-            let fuzzer_type: FuzzerType = conf_filename_split[1].parse().map_err(|e| {
-                format!("failed to parse {}: {}", conf_filename_split[1], e)
+            let adapter = adapters.get(&fuzzer.fuzzer_type).map_err(|e| {
+                format!("invalid type for fuzzer {}: {}", fuzzer_id, e)
             })?;
 
-            let wp = WORK_PATH.to_string();
+            if let Some(ref endpoint) = fuzzer.endpoint {
+                let mut driver = Driver::new_remote(fuzzer_id.clone(), adapter, endpoint.clone());
+                if let Some(batch) = batch_for(fuzzer, &mut driver, high)? {
+                    batches.insert(fuzzer_id.clone(), batch);
+                }
+                drivers_map.insert(fuzzer_id, driver);
+                continue;
+            }
 
-            // set sut input filename (if used)
-            let sut_input_file = if matches.opt_present("s") { None }
-                else { Some(format!("{}/.{}.input", wp, fuzzer_id)) };
+            let driver_port = fuzzer.metric_port
+                .or_else(|| next_metric_port(&mut metric_port, &explicit_ports));
+            let mut driver = local_driver(&config, &fuzzer_id, adapter.clone(), driver_port,
+                &transport);
 
-            // set input filename (if any) in sut arguments, replacing any '@@' occurrence
-            let sut = matches.free.iter().map(|s| {
-                if s == "@@" { sut_input_file.clone().unwrap_or(s.to_string()) }
-                else { s.to_string() }
-            }).collect();
+            // a command in the campaign file takes the place of the id.type.conf file
+            if let Some(ref command) = fuzzer.command {
+                let cmd_filename = format!("{}/.{}.{}.conf", wp, fuzzer_id, adapter.name());
+                write_command(&cmd_filename, command)?;
+                driver.set_fuzzer_cmd_filename(cmd_filename);
+            }
+            driver.locate_paths()?;
+            driver.override_paths(fuzzer.corpus.clone(), fuzzer.inject.clone());
+            if let Some(batch) = batch_for(fuzzer, &mut driver, high)? {
+                batches.insert(fuzzer_id.clone(), batch);
+            }
 
-            drivers_map.insert(fuzzer_id.clone(),
-                Driver::with_defaults(
-                    fuzzer_id, fuzzer_type, sut, sut_input_file, metric_port, wp,
-                    bb_script.clone(), section_name.clone()));
+            // secondary instances get drivers of their own (with their data in the directory of
+            // the fuzzer) reporting what they find, while inputs are evaluated and injected
+            // through the driver of the fuzzer only
+            let secondaries = fuzzer.secondaries.unwrap_or(0);
+            let command = if secondaries > 0 { driver.read_command()? } else { vec![] };
+            for i in 1..secondaries + 1 {
+                let member_id = format!("{}-S{}", fuzzer_id, i);
+                if drivers_map.contains_key(&member_id) {
+                    return Err(format!("fuzzer {} is defined more than once", member_id));
+                }
 
-            metric_port += 1;
-        }
+                let member_command = adapter.secondary_command(&command, &member_id)
+                    .ok_or(format!("fuzzers of type {} cannot run secondary instances",
+                        adapter.name()))?;
+                let cmd_filename = format!("{}/.{}.{}.conf", wp, member_id, adapter.name());
+                write_command(&cmd_filename, &member_command)?;
 
-        let winning_strategy = match matches.opt_str("t") {
-            Some(threshold_str) => {
-                let threshold = threshold_str.parse().map_err(|e| {
-                    format!("unable to parse {} as threshold: {}", threshold_str, e)
+                let data_path = format!("{}/{}/{}.driver", wp, fuzzer_id, member_id);
+                fs::create_dir_all(&data_path).map_err(|e| {
+                    format!("failed to create {}: {}", data_path, e)
                 })?;
-                WinningStrategy::MultipleWinners(threshold, matches.opt_present("H"))
-            },
-            None => WinningStrategy::SingleWinner(matches.opt_present("H"))
+
+                let member_port = next_metric_port(&mut metric_port, &explicit_ports);
+                let mut member = local_driver(&config, &member_id, adapter.clone(), member_port,
+                    &transport);
+                member.set_fuzzer_cmd_filename(cmd_filename);
+                member.set_data_path(data_path);
+                member.locate_paths()?;
+                member.override_paths(None, fuzzer.inject.clone());
+                // only the fuzzer is told when it is ready for inputs
+                member.set_readiness_path(None);
+
+                drivers_map.insert(member_id.clone(), member);
+                group_leaders.insert(member_id, fuzzer_id.clone());
+            }
+
+            drivers_map.insert(fuzzer_id, driver);
+        }
+
+        let blob_store = match config.inline {
+            Some(limit) => Some(BlobStore::new(format!("{}/blobs", wp), limit)?),
+            None => None
         };
 
         let m = Master {
-            sut: matches.free.clone(),
+            sut: config.sut.clone(),
             winning_strategy: winning_strategy,
+            adapters,
             drivers: drivers_map,
+            group_leaders,
+            batches,
             processes: HashMap::new(),
+            supervision: HashMap::new(),
+            max_restarts: config.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS),
+            grace_period: Duration::seconds(
+                config.grace_period.map(|t| t as i64).unwrap_or(DEFAULT_GRACE_PERIOD_SECS)),
+            stop_conditions: StopConditions::from_config(&config.stop)?,
+            crash_counter: CrashCounter::new(),
+            coverage_map: CoverageMap::new(),
+            local_coverage: if config.local_metric { Some(CoverageTable::new()) } else { None },
+            metrics,
+            known_signatures: HashMap::new(),
+            duplicates: HashMap::new(),
+            last_interesting: Duration::zero(),
+            last_coverage_increase: Duration::zero(),
+            won_counts: HashMap::new(),
+            interesting_port: config.ports.interesting,
+            use_port: config.ports.use_,
+            registration_port: config.ports.registration,
+            transport,
+            context: zmq::Context::new(),
             interesting_pull: None,
             use_pub: None,
+            registration_rep: None,
             metric_reqs: HashMap::new(),
+            remote_missed: HashMap::new(),
+            metric_timeout: Duration::milliseconds(
+                config.metric_timeout.map(|t| t as i64).unwrap_or(DEFAULT_METRIC_TIMEOUT_MS)),
+            blob_store,
+            resume: config.resume,
+            time_offset: Duration::zero(),
             start_time: None,
-            work_path: WORK_PATH.to_string(),
+            work_path: wp,
             interesting_log: vec![],
             interesting_log_file: None,
-            winning_log_file: None
+            winning_log_file: None,
+            strategy_log_file: None,
+            dedup_log_file: None,
+            dashboard: Dashboard::new(!config.plain),
+            events_log_file: None
         };
 
         Ok(m)
     }
 
+    // ask drivers to terminate (so that they can tear down their fuzzers), kill the ones still
+    // running after the grace period and finally flush the logs to disk
     fn stop(&mut self) {
-        for process in self.processes.values_mut() {
-            match process.try_wait() {
-                Ok(Some(_)) => (),
-                Ok(None) => process.kill().expect("failed to kill driver"),
-                Err(e) => panic!(e)
+        for (fuzzer_id, process) in &mut self.processes {
+            if let Ok(None) = process.try_wait() {
+                if let Err(e) = kill(Pid::from_raw(-(process.id() as i32)), Signal::SIGTERM) {
+                    warn!("failed to terminate {}: {}", fuzzer_id, e);
+                }
+            }
+        }
+
+        info!("waiting up to {}s for drivers to exit", self.grace_period.num_seconds());
+        let stop_time = PreciseTime::now();
+        loop {
+            let mut running = 0;
+            for (fuzzer_id, process) in &mut self.processes {
+                match process.try_wait() {
+                    Ok(Some(_)) => (),
+                    Ok(None) => running += 1,
+                    Err(e) => error!("failed waiting for {}: {}", fuzzer_id, e)
+                }
+            }
+
+            if running == 0 || stop_time.to(PreciseTime::now()) >= self.grace_period {
+                break;
+            }
+
+            thread::sleep(time::Duration::from_millis(100));
+        }
+
+        for (fuzzer_id, process) in &mut self.processes {
+            if let Ok(None) = process.try_wait() {
+                warn!("killing {}", fuzzer_id);
+                let kill_res = kill(Pid::from_raw(-(process.id() as i32)), Signal::SIGKILL)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| process.wait().map(drop).map_err(|e| e.to_string()));
+                if let Err(e) = kill_res {
+                    error!("failed to kill {}: {}", fuzzer_id, e);
+                }
+            }
+        }
+
+        let log_files = vec![&mut self.interesting_log_file, &mut self.winning_log_file,
+            &mut self.strategy_log_file, &mut self.dedup_log_file, &mut self.events_log_file];
+        for file_opt in log_files {
+            if let Some(ref mut file) = *file_opt {
+                if let Err(e) = file.flush().and_then(|_| file.sync_all()) {
+                    error!("failed to sync log file: {}", e);
+                }
             }
         }
     }
@@ -222,43 +761,83 @@ impl Master {
     pub fn start(&mut self) {
         info!("starting master (SUT {})", self.sut.first().unwrap());
 
-        let context = zmq::Context::new();
-
-        {   // bind to interesting_port in PULL (pull interesting inputs)
-            let socket = context.socket(zmq::PULL).expect("failed to create interesting socket");
-            let address = &format!("{}:{}", BIND_ADDR, INTERESTING_PORT);
-            socket.bind(address).expect(&format!("failed to bind interesting socket to {}", address));
-            info!("bind 'interesting' socket {}", address);
-            self.interesting_pull = Some(socket);
+        // bind PULL (pull interesting inputs) and PUB (publish input to use) sockets, then tell
+        // the drivers where to find them
+        let interesting_endpoint = self.transport.bind_endpoint("interesting",
+            self.interesting_port);
+        let use_endpoint = self.transport.bind_endpoint("use", self.use_port);
+        let bind_res = self.bind_socket(zmq::PULL, "interesting", &interesting_endpoint)
+            .and_then(|interesting| {
+                self.bind_socket(zmq::PUB, "use", &use_endpoint).map(|used| (interesting, used))
+            });
+        let ((interesting_pull, interesting_endpoint), (use_pub, use_endpoint)) = match bind_res {
+            Ok(sockets) => sockets,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+        self.interesting_pull = Some(interesting_pull);
+        self.use_pub = Some(use_pub);
+        let interesting_endpoint = self.transport.connect_endpoint(&interesting_endpoint);
+        let use_endpoint = self.transport.connect_endpoint(&use_endpoint);
+        for driver in self.drivers.values_mut() {
+            driver.set_master_endpoints(interesting_endpoint.clone(), use_endpoint.clone());
         }
 
-        {   // bind to use_port in PUB (publish input to use)
-            let socket = context.socket(zmq::PUB).expect("failed to create use socket");
-            let address = &format!("{}:{}", BIND_ADDR, USE_PORT);
-            socket.bind(address).expect(&format!("failed to bind use socket to {}", address));
-            info!("bind 'use' socket {}", address);
-            self.use_pub = Some(socket);
+        if let Some(registration_port) = self.registration_port {
+            // bind REP (drivers joining or leaving the campaign), always over tcp
+            let endpoint = Transport::Tcp.bind_endpoint("registration", Some(registration_port));
+            match self.bind_socket(zmq::REP, "registration", &endpoint) {
+                Ok((socket, _)) => self.registration_rep = Some(socket),
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            }
         }
 
-        // open inputs log file
+        // open log files, after recovering the state from the ones of the interrupted run
         let interesting_log_filename = format!("{}/inputs.log", self.work_path);
-        match File::create(&interesting_log_filename) {
-            Ok(file) => self.interesting_log_file = Some(file),
-            Err(error) => {
-                error!("failed to open {}: {}", interesting_log_filename, error);
+        let winning_log_filename = format!("{}/winning.log", self.work_path);
+        let strategy_log_filename = format!("{}/strategy.log", self.work_path);
+        let dedup_log_filename = format!("{}/dedup.log", self.work_path);
+        let events_log_filename = format!("{}/events.log", self.work_path);
+
+        // the coverage log is written again from the recovered inputs
+        let coverage_log_filename = format!("{}/coverage.live.log", self.work_path);
+        let log_fuzzer_ids = self.participants();
+        if let Err(e) = self.coverage_map.open_log(&coverage_log_filename, log_fuzzer_ids) {
+            error!("{}", e);
+            return;
+        }
+
+        if self.resume {
+            let recover_res = self.recover_campaign(&interesting_log_filename,
+                &winning_log_filename,
+                &[&strategy_log_filename, &dedup_log_filename, &events_log_filename]);
+            if let Err(e) = recover_res {
+                error!("failed to resume campaign: {}", e);
                 return;
             }
         }
 
-        // open winning log file
-        let winning_log_filename = format!("{}/winning.log", self.work_path);
-        match File::create(&winning_log_filename) {
-            Ok(file) => self.winning_log_file = Some(file),
-            Err(error) => {
-                error!("failed to open {}: {}", winning_log_filename, error);
+        let log_files_res: Result<Vec<_>, _> = [&interesting_log_filename,
+            &winning_log_filename, &strategy_log_filename, &dedup_log_filename,
+            &events_log_filename].iter()
+            .map(|f| open_log(f, self.resume)).collect();
+        let mut log_files = match log_files_res {
+            Ok(log_files) => log_files.into_iter(),
+            Err(e) => {
+                error!("{}", e);
                 return;
             }
-        }
+        };
+        self.interesting_log_file = log_files.next();
+        self.winning_log_file = log_files.next();
+        self.strategy_log_file = log_files.next();
+        self.dedup_log_file = log_files.next();
+        self.events_log_file = log_files.next();
 
         self.start_time = Some(PreciseTime::now());
 
@@ -270,14 +849,28 @@ impl Master {
                 return;
             }
         };
-        let mut watch_descriptors: HashMap<WatchDescriptor, WatchDescriptorData> = HashMap::new();
+        let mut watch_descriptors: HashMap<WatchDescriptor, ReadinessWatch> = HashMap::new();
         let mut inotify_buffer = [0u8; 4096];
         for (fuzzer_id, driver) in &self.drivers {
-            self.processes.insert(fuzzer_id.clone(), driver.spawn());
+            if driver.is_remote() {
+                continue;
+            }
+
+            let spawn_res = if self.resume { driver.respawn() } else { driver.spawn() };
+            match spawn_res {
+                Ok(process) => self.processes.insert(fuzzer_id.clone(), process),
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            };
+            self.supervision.insert(fuzzer_id.clone(),
+                DriverSupervision { failures: 0, restart_at: None });
             info!("started {}", fuzzer_id);
 
-            if driver.is_vuzzer() {
-                let path_to_watch = format!("{}/{}", WORK_PATH, fuzzer_id);
+            if let Some(readiness_path) = driver.get_readiness_path() {
+                let readiness_path = Path::new(readiness_path);
+                let path_to_watch = readiness_path.parent().unwrap_or(Path::new("."));
                 let wd = match inotify.add_watch(path_to_watch, WatchMask::MODIFY) {
                     Ok(wd) => wd,
                     Err(e) => {
@@ -285,54 +878,51 @@ impl Master {
                         return;
                     }
                 };
-                let wd_data = WatchDescriptorData {
+                let watch = ReadinessWatch {
                     fuzzer_id: fuzzer_id.clone(),
-                    modified: 0,
-                    ready: false
+                    file_name: readiness_path.file_name().map(|f| f.to_string_lossy().to_string())
+                        .unwrap_or_default()
                 };
-                watch_descriptors.insert(wd, wd_data);
+                watch_descriptors.insert(wd, watch);
             }
         }
 
-        // setup ctrlc handler
+        // setup ctrlc handler (drivers are stopped once the main loop exits, as their pids change
+        // when they get restarted)
         let interrupted = Arc::new(AtomicBool::new(false));
         {
             let interrupted_clone = Arc::clone(&interrupted);
-            let pids = self.processes.values_mut().map(|c| c.id()).collect::<Vec<u32>>().clone();
             ctrlc::set_handler(move || {
                 interrupted_clone.store(true, Ordering::Relaxed);
-                for pid in &pids {
-                    kill(Pid::from_raw(*pid as i32), Signal::SIGKILL)
-                        .expect("failed to kill driver");
-                }
             }).expect("failed to set interrupt handler");
         }
 
-        // connect to metric_port in REQ for each driver (requests metric to driver)
-        for (fuzzer_id, driver) in &self.drivers {
-            let socket = context.socket(zmq::REQ).expect("failed to create metric socket");
-            let address = &format!("{}:{}", CONN_ADDR, driver.get_metric_port());
-            socket.connect(address).expect(&format!("failed to connecto to metric socket {}", address));
-            self.metric_reqs.insert(fuzzer_id.clone(), socket);
-        }
-
-        // this table contains the best interesting input for slower fuzzers that need throttling
-        let mut best_interesting: BestInterestingMap = HashMap::new();
-        for wdata in watch_descriptors.values() {
-            best_interesting.insert(wdata.fuzzer_id.clone(), vec![]);
+        // connect in REQ to the metric server of drivers running elsewhere (requests metric to
+        // driver), local ones are connected to once they say hello with their metric port
+        let fuzzer_ids: Vec<_> = self.drivers.iter().filter(|t| t.1.is_remote())
+            .map(|t| t.0.clone()).collect();
+        for fuzzer_id in fuzzer_ids {
+            if let Err(e) = self.connect_metric_socket(&fuzzer_id) {
+                error!("{}", e);
+                return;
+            }
         }
 
-        let high_strategy = self.get_high_strategy();
         let mut pulled_interesting = false;
         let mut pending_newline = false;
+        let mut last_strategy_dump = PreciseTime::now();
+        let mut last_crash_scan = PreciseTime::now();
+        let mut stop_reason = None;
+        self.dashboard.open();
         'outer: while !interrupted.load(Ordering::Relaxed) {
             // check drivers liveness
+            let mut exited = vec![];
             for (fuzzer_id, process) in &mut self.processes {
                 match process.try_wait() {
                     Ok(Some(status)) => {
                         let status_str = if status.success() {"normally"} else {"with error"};
                         warn!("{} exited {}", fuzzer_id, status_str);
-                        break 'outer;
+                        exited.push((fuzzer_id.clone(), status_str));
                     },
                     Ok(None) => (),
                     Err(e) => {
@@ -342,18 +932,55 @@ impl Master {
                 }
             }
 
-            // check inotify events
+            for (fuzzer_id, status_str) in exited {
+                if let Err(e) = self.driver_exited(&fuzzer_id, status_str) {
+                    error!("{}", e);
+                    break 'outer;
+                }
+            }
+
+            // restart drivers whose backoff expired, their throttling state starts over
+            match self.restart_drivers() {
+                Ok(restarted) => {
+                    for fuzzer_id in restarted {
+                        if let Some(batch) = self.batches.get_mut(&fuzzer_id) {
+                            batch.reset();
+                        }
+                    }
+                },
+                Err(e) => {
+                    error!("{}", e);
+                    break 'outer;
+                }
+            }
+
+            // let drivers started elsewhere join or leave
+            if let Err(e) = self.serve_registrations() {
+                error!("{}", e);
+                break;
+            }
+
+            // check inotify events, for batched fuzzers whose driver does not tell they are ready
             match inotify.read_events(&mut inotify_buffer) {
                 Ok(events) => {
                     for event in events {
-                        if event.name.is_some() && event.name.unwrap() == "stats.log" {
-                            let wd_data = watch_descriptors.get_mut(&event.wd).unwrap();
-                            wd_data.modified += 1;
-                            if wd_data.modified > 1 {
-                                wd_data.ready = true;
+                        let watch = &watch_descriptors[&event.wd];
+                        if event.name.map(|n| n.to_string_lossy() != watch.file_name)
+                            .unwrap_or(true)
+                        {
+                            continue;
+                        }
+
+                        if let Some(batch) = self.batches.get_mut(&watch.fuzzer_id) {
+                            if batch.notified {
+                                continue;
+                            }
+                            batch.modified += 1;
+                            if batch.modified > 1 {
+                                batch.ready = true;
                             }
                             debug!("inotify event on {} (modified {})",
-                                wd_data.fuzzer_id, wd_data.modified);
+                                watch.fuzzer_id, batch.modified);
                         }
                     }
                 },
@@ -363,107 +990,567 @@ impl Master {
                 }
             };
 
-            // try pulling new interesting input and process any
-            match self.pull_interesting() {
-                Ok(Some(interesting)) => {
+            // try pulling new messages from drivers and process any interesting input
+            match self.pull_driver_message() {
+                Ok(Some(DriverMessage::Hello(hello))) => {
+                    if let Err(e) = self.driver_hello(&hello) {
+                        error!("{}", e);
+                        break;
+                    }
+                },
+                Ok(Some(DriverMessage::Ready(ready))) => self.driver_ready(&ready),
+                Ok(Some(DriverMessage::Interesting(interesting))) => {
                     if pending_newline {
                         print!("\r");
                         pending_newline = false;
                     }
                     pulled_interesting = true;
-                    if let Err(e) = self.log_interesting(&interesting) {
+                    let signature = input_signature(&interesting);
+                    if let Err(e) = self.log_interesting(&interesting, signature) {
                         error!("failed logging: {}", e);
                         break;
                     }
+                    let elapsed_time = self.elapsed_time();
+                    self.update_coverage(&interesting, elapsed_time);
 
-                    let process_res = self.process_interesting(interesting,
-                        watch_descriptors.values(), &mut best_interesting);
+                    let process_res = self.process_interesting(interesting, signature);
                     if let Err(e) = process_res {
                         error!("failed to process interesting: {}", e);
                         break;
                     }
                 },
+                Ok(None) if self.dashboard.is_enabled() => (),
                 Ok(None) => {
                     if !pulled_interesting && pending_newline {
                         print!("\r");
                     }
-                    let t = self.start_time.unwrap().to(PreciseTime::now());
-                    print!("{:02}:{:02}:{:02}",
-                        t.num_hours(), t.num_minutes() % 60, t.num_seconds() % 60);
+                    let t = self.elapsed_time();
+                    print!("{:02}:{:02}:{:02} - {} branches",
+                        t.num_hours(), t.num_minutes() % 60, t.num_seconds() % 60,
+                        self.coverage_map.global_len());
                     pulled_interesting = false;
                     pending_newline = true;
                 },
                 Err(e) => {
-                    error!("failed to pull interesting: {}", e);
+                    error!("failed to pull driver message: {}", e);
                     break;
                 }
             }
 
-            // check if watched fuzzers are ready and send the best of collected interesting inputs
-            for wdata in watch_descriptors.values_mut() {
-                if !wdata.ready { continue; }
-
-                let best_vec = best_interesting.get_mut(&wdata.fuzzer_id).unwrap();
-                best_vec.sort_unstable_by(|t1, t2| {
-                    let m1 = &t1.1.metric;
-                    let m2 = &t2.1.metric;
-                    m1.partial_cmp(m2).unwrap_or(::std::cmp::Ordering::Equal)
-                });
-
-                let best = (if high_strategy { best_vec.last() } else { best_vec.first() })
-                    .map(|t| t.to_owned());
-
-                if let Some((ref b, ref m)) = best {
-                    let assign_res = self.assign_input(b, &[wdata.fuzzer_id.clone()]);
-                    if let Err(e) = assign_res {
-                        error!("failed to assign to {}: {}", wdata.fuzzer_id, e);
-                        break 'outer;
-                    }
+            // send batched fuzzers that are ready the best of the interesting inputs collected
+            let mut ready_ids: Vec<String> = self.batches.iter().filter(|t| t.1.ready)
+                .map(|t| t.0.clone()).collect();
+            ready_ids.sort();
+            for fuzzer_id in ready_ids {
+                let elapsed_time = self.elapsed_time();
+                let best = self.batches.get_mut(&fuzzer_id).unwrap().take(elapsed_time);
+                for (b, m, signature) in best {
                     if pending_newline {
                         print!("\r");
+                        pending_newline = false;
                     }
-                    let t = self.start_time.unwrap().to(PreciseTime::now());
-                    println!("{:02}:{:02}:{:02} - {} - {} {}",
-                        t.num_hours(), t.num_minutes() % 60, t.num_seconds() % 60,
-                        b.fuzzer_id, wdata.fuzzer_id, m.metric);
-                    pending_newline = false;
-                    wdata.ready = false;
-                    best_vec.clear();
-
-                    if let Some(ref mut file) = self.winning_log_file {
-                        let line = format!("{}{sep}{}{sep}{}\n",
-                            t.num_milliseconds(), b.fuzzer_id, wdata.fuzzer_id,
-                            sep=LOG_LINE_SEPARATOR);
-                        if let Err(e) = file.write_all(line.as_bytes()) {
-                            error!("failed writing to {}: {}", winning_log_filename, e);
-                            break 'outer;
-                        }
+                    if !self.dashboard.is_enabled() {
+                        println!("{:02}:{:02}:{:02} - {} - {} {}",
+                            elapsed_time.num_hours(), elapsed_time.num_minutes() % 60,
+                            elapsed_time.num_seconds() % 60, b.fuzzer_id, fuzzer_id, m);
+                    }
+
+                    let deliver_res = self.deliver(&b, slice::from_ref(&fuzzer_id), elapsed_time,
+                        signature);
+                    if let Err(e) = deliver_res {
+                        error!("failed to deliver to {}: {}", fuzzer_id, e);
+                        break 'outer;
                     }
                 }
             }
 
+            if self.dashboard.is_due() {
+                self.draw_dashboard();
+            }
+
+            // periodically dump the strategy state and the duplicates counters
+            let since_dump = last_strategy_dump.to(PreciseTime::now());
+            if since_dump.num_seconds() >= STRATEGY_DUMP_INTERVAL_SECS {
+                last_strategy_dump = PreciseTime::now();
+                if let Err(e) = self.log_strategy_state() {
+                    error!("failed writing to {}: {}", strategy_log_filename, e);
+                    break;
+                }
+                if let Err(e) = self.log_duplicates() {
+                    error!("failed writing to {}: {}", dedup_log_filename, e);
+                    break;
+                }
+            }
+
+            // check whether the campaign should end
+            let since_scan = last_crash_scan.to(PreciseTime::now());
+            if self.stop_conditions.needs_crashes() &&
+                since_scan.num_seconds() >= CRASH_SCAN_INTERVAL_SECS
+            {
+                last_crash_scan = PreciseTime::now();
+                if let Err(e) = self.scan_crashes() {
+                    error!("{}", e);
+                    break;
+                }
+            }
+
+            let progress = CampaignProgress {
+                elapsed_time: self.elapsed_time(),
+                last_interesting: self.last_interesting,
+                last_coverage_increase: self.last_coverage_increase,
+                unique_crashes: self.crash_counter.unique_crashes()
+            };
+            stop_reason = self.stop_conditions.check(&progress);
+            if stop_reason.is_some() {
+                break;
+            }
+
             thread::sleep(time::Duration::from_millis(10));
         }
 
-        if !interrupted.load(Ordering::Relaxed) {
-            self.stop();
+        self.dashboard.close();
+        if pending_newline {
+            println!();
+        }
+
+        let reason = stop_reason.unwrap_or(
+            if interrupted.load(Ordering::Relaxed) { "interrupted" } else { "error" }.to_string());
+        info!("stopping campaign: {}", reason);
+        if let Err(e) = self.log_event("stop", "master", &reason.replace(" ", "_")) {
+            error!("{}", e);
+        }
+
+        self.stop();
+
+        if let Err(e) = self.scan_crashes() {
+            error!("{}", e);
+        }
+        self.print_summary(&reason);
+    }
+
+    // time since the campaign started, including the time it ran before being resumed
+    fn elapsed_time(&self) -> Duration {
+        self.time_offset + self.start_time.unwrap().to(PreciseTime::now())
+    }
+
+    // rebuild the state of an interrupted campaign from its logs, so that the campaign time and
+    // counters continue from where they stopped
+    fn recover_campaign(&mut self, interesting_log_filename: &str, winning_log_filename: &str,
+        other_log_filenames: &[&str])
+        -> Result<(), String>
+    {
+        for line in read_log_lines(interesting_log_filename)? {
+            let interesting: InterestingWithTime = match line.parse() {
+                Ok(interesting) => interesting,
+                Err(e) => {
+                    warn!("skipping line of {}: {}", interesting_log_filename, e);
+                    continue;
+                }
+            };
+
+            self.time_offset = self.time_offset.max(interesting.elapsed_time);
+            self.last_interesting = interesting.elapsed_time;
+            self.update_coverage(&interesting.input_message, interesting.elapsed_time);
+            // the inputs given to a fuzzer are not logged with their coverage, only the ones it
+            // found count as known again
+            if let Some(signature) = input_signature(&interesting.input_message) {
+                let sender = self.participant(&interesting.input_message.fuzzer_id).to_string();
+                self.known_signatures.entry(sender).or_default().insert(signature);
+            }
+            self.interesting_log.push(interesting);
+        }
+
+        for line in read_log_lines(winning_log_filename)? {
+            let winners_opt = line.split(LOG_LINE_SEPARATOR).nth(2);
+            match (parse_log_time(&line), winners_opt) {
+                (Ok(elapsed_time), Some(winners)) => {
+                    self.time_offset = self.time_offset.max(elapsed_time);
+                    for fuzzer_id in winners.split(WINNERS_SEPARATOR) {
+                        *self.won_counts.entry(fuzzer_id.to_string()).or_insert(0) += 1;
+                    }
+                },
+                _ => warn!("skipping line of {}: '{}'", winning_log_filename, line)
+            }
+        }
+
+        for filename in other_log_filenames {
+            for line in read_log_lines(filename)? {
+                match parse_log_time(&line) {
+                    Ok(elapsed_time) => self.time_offset = self.time_offset.max(elapsed_time),
+                    Err(e) => warn!("skipping line of {}: {}", filename, e)
+                }
+            }
+        }
+
+        info!("resuming campaign after {}s ({} interesting inputs, {} branches covered)",
+            self.time_offset.num_seconds(), self.interesting_log.len(),
+            self.coverage_map.global_len());
+
+        Ok(())
+    }
+
+    fn update_coverage(&mut self, interesting_input: &InterestingInput, elapsed_time: Duration) {
+        let branches: Vec<Branch> = match read_structs(&interesting_input.coverage_path) {
+            Ok(branches) => branches,
+            Err(e) => {
+                warn!("failed to read coverage from {}: {}", interesting_input.coverage_path, e);
+                return;
+            }
+        };
+
+        // the inputs of secondary instances count for the group
+        let fuzzer_id = self.participant(&interesting_input.fuzzer_id).to_string();
+        if let Some(ref mut local_coverage) = self.local_coverage {
+            local_coverage.found(&fuzzer_id, &metric::count_hits(&branches));
+        }
+
+        match self.coverage_map.add(&fuzzer_id, &branches, elapsed_time) {
+            Ok(true) => self.last_coverage_increase = elapsed_time,
+            Ok(false) => (),
+            Err(e) => warn!("{}", e)
         }
     }
 
-    fn pull_interesting(&self) -> Result<Option<InterestingInput>, String> {
-        match self.interesting_pull.as_ref().unwrap().recv_bytes(zmq::DONTWAIT) {
-            Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).parse()?)),
-            Err(zmq::Error::EAGAIN) => Ok(None),
-            Err(error) => Err(error.to_string())
+    fn scan_crashes(&mut self) -> Result<(), String> {
+        // the crash directories of remote drivers are not reachable
+        for driver in self.drivers.values().filter(|d| !d.is_remote()) {
+            self.crash_counter.scan(driver.get_crash_path(), |f| driver.is_crash_file(f))?;
         }
+
+        Ok(())
+    }
+
+    // the fuzzers taking part in the campaign, secondary instances being part of their group
+    fn participants(&self) -> Vec<String> {
+        let mut fuzzer_ids: Vec<_> = self.drivers.keys()
+            .filter(|f| !self.group_leaders.contains_key(*f)).cloned().collect();
+        fuzzer_ids.sort();
+        fuzzer_ids
+    }
+
+    fn interesting_count(&self, participant: &str) -> usize {
+        self.interesting_log.iter()
+            .filter(|i| self.participant(&i.input_message.fuzzer_id) == participant).count()
     }
 
-    fn log_interesting(&mut self, interesting_input: &InterestingInput)
+    fn restart_count(&self, participant: &str) -> u32 {
+        self.supervision.iter().filter(|t| self.participant(t.0) == participant)
+            .map(|t| t.1.failures).sum()
+    }
+
+    fn draw_dashboard(&mut self) {
+        let elapsed_time = self.elapsed_time();
+        let rows: Vec<_> = self.participants().into_iter().map(|fuzzer_id| {
+            let supervision = self.supervision.get(&fuzzer_id);
+            let connected = self.metric_reqs.contains_key(&fuzzer_id);
+            let liveness = match supervision.and_then(|s| s.restart_at) {
+                Some(restart_at) => {
+                    format!("restart in {}s", (restart_at - elapsed_time).num_seconds().max(0))
+                },
+                None if self.drivers[&fuzzer_id].is_remote() => "remote".to_string(),
+                None if connected || self.group_leaders.contains_key(&fuzzer_id) => {
+                    "running".to_string()
+                },
+                None => "starting".to_string()
+            };
+
+            FuzzerRow {
+                liveness,
+                restarts: self.restart_count(&fuzzer_id),
+                interesting: self.interesting_count(&fuzzer_id),
+                injected: self.won_counts.get(&fuzzer_id).cloned().unwrap_or(0),
+                branches: self.coverage_map.fuzzer_len(&fuzzer_id),
+                batched: self.batches.get(&fuzzer_id).map(|b| b.len()),
+                fuzzer_id
+            }
+        }).collect();
+
+        self.dashboard.draw(elapsed_time, self.coverage_map.global_len(),
+            self.interesting_log.len(), self.crash_counter.unique_crashes(), &rows);
+    }
+
+    fn print_summary(&self, reason: &str) {
+        let t = self.elapsed_time();
+        println!("campaign ended after {:02}:{:02}:{:02}: {}",
+            t.num_hours(), t.num_minutes() % 60, t.num_seconds() % 60, reason);
+
+        for fuzzer_id in self.participants() {
+            // executions as counted by fuzzers keeping statistics, over the whole group
+            let execs: Vec<u64> = self.drivers.iter()
+                .filter(|t| self.participant(t.0) == fuzzer_id)
+                .filter_map(|t| t.1.read_stats().get("execs_done").and_then(|e| e.parse().ok()))
+                .collect();
+            let execs = if execs.is_empty() { String::new() }
+                else { format!(" - {} execs", execs.iter().sum::<u64>()) };
+            println!("{} - {} interesting - {} branches - {} won - {} restarts{}", fuzzer_id,
+                self.interesting_count(&fuzzer_id), self.coverage_map.fuzzer_len(&fuzzer_id),
+                self.won_counts.get(&fuzzer_id).unwrap_or(&0), self.restart_count(&fuzzer_id),
+                execs);
+        }
+
+        println!("{} interesting inputs - {} branches covered - {} duplicates not shared - {} \
+            unique crashes", self.interesting_log.len(), self.coverage_map.global_len(),
+            self.duplicates.values().sum::<usize>(),
+            self.crash_counter.unique_crashes());
+    }
+
+    // schedule the restart of a driver that exited, unless it failed too many times already
+    fn driver_exited(&mut self, fuzzer_id: &str, status_str: &str) -> Result<(), String> {
+        self.processes.remove(fuzzer_id);
+        self.metric_reqs.remove(fuzzer_id);
+
+        let elapsed_time = self.elapsed_time();
+        let failures = {
+            let supervision = self.supervision.get_mut(fuzzer_id).unwrap();
+            supervision.failures += 1;
+            supervision.failures
+        };
+
+        if failures > self.max_restarts {
+            self.log_event("gave_up", fuzzer_id, &failures.to_string())?;
+            return Err(format!("{} exited {} times, giving up", fuzzer_id, failures));
+        }
+
+        let backoff_secs = (RESTART_BACKOFF_START_SECS << (failures - 1).min(16))
+            .min(RESTART_BACKOFF_MAX_SECS);
+        self.supervision.get_mut(fuzzer_id).unwrap().restart_at =
+            Some(elapsed_time + Duration::seconds(backoff_secs));
+
+        info!("restarting {} in {}s", fuzzer_id, backoff_secs);
+        self.log_event("exited", fuzzer_id, &status_str.replace(" ", "_"))
+    }
+
+    // respawn the drivers whose restart is due, returning their ids
+    fn restart_drivers(&mut self) -> Result<Vec<String>, String> {
+        let elapsed_time = self.elapsed_time();
+        let due: Vec<_> = self.supervision.iter().filter(|t| {
+            t.1.restart_at.map(|r| r <= elapsed_time).unwrap_or(false)
+        }).map(|t| t.0.clone()).collect();
+
+        for fuzzer_id in &due {
+            let process = self.drivers[fuzzer_id].respawn()?;
+            self.processes.insert(fuzzer_id.clone(), process);
+
+            let failures = {
+                let supervision = self.supervision.get_mut(fuzzer_id).unwrap();
+                supervision.restart_at = None;
+                supervision.failures
+            };
+
+            info!("restarted {}", fuzzer_id);
+            self.log_event("restarted", fuzzer_id, &failures.to_string())?;
+        }
+
+        Ok(due)
+    }
+
+    // reply to registration requests until there are no more pending
+    fn serve_registrations(&mut self) -> Result<(), String> {
+        loop {
+            let frames = match self.registration_rep {
+                Some(ref socket) => match socket.recv_multipart(zmq::DONTWAIT) {
+                    Ok(frames) => frames,
+                    Err(zmq::Error::EAGAIN) => return Ok(()),
+                    Err(e) => return Err(format!("error receiving registration: {}", e))
+                },
+                None => return Ok(())
+            };
+
+            let result = match RegistrationMessage::from_frames(&frames) {
+                Ok(RegistrationMessage::Register(registration)) => self.driver_joined(registration),
+                Ok(RegistrationMessage::Unregister(fuzzer_id)) => {
+                    self.driver_left(&fuzzer_id, "unregistered")
+                },
+                Err(e) => Err(e)
+            };
+            if let Err(ref e) = result {
+                warn!("rejected registration message: {}", e);
+            }
+
+            let reply_frames = RegistrationMessage::reply_frames(&result);
+            self.registration_rep.as_ref().unwrap()
+                .send_multipart(&messages::frame_slices(&reply_frames), 0)
+                .map_err(|e| format!("error replying to registration: {}", e))?;
+        }
+    }
+
+    // add a driver started elsewhere, a driver registering again replaces its previous endpoint
+    fn driver_joined(&mut self, registration: Registration) -> Result<(), String> {
+        let fuzzer_id = registration.fuzzer_id;
+        check_fuzzer_id(&fuzzer_id)?;
+        if self.drivers.get(&fuzzer_id).map(|d| !d.is_remote()).unwrap_or(false) {
+            return Err(format!("{} is the id of a local driver", fuzzer_id));
+        }
+
+        let adapter = self.adapters.get(&registration.fuzzer_type).map_err(|e| {
+            format!("invalid type for fuzzer {}: {}", fuzzer_id, e)
+        })?;
+
+        let driver = Driver::new_remote(fuzzer_id.clone(), adapter,
+            registration.metric_endpoint.clone());
+        if driver.is_batched() {
+            let batch = Batch::new(DEFAULT_BATCH_SIZE, None, self.winning_strategy.is_high());
+            self.batches.insert(fuzzer_id.clone(), batch);
+        } else {
+            self.batches.remove(&fuzzer_id);
+        }
+        self.drivers.insert(fuzzer_id.clone(), driver);
+        self.remote_missed.remove(&fuzzer_id);
+        self.connect_metric_socket(&fuzzer_id)?;
+
+        info!("{} joined ({})", fuzzer_id, registration.metric_endpoint);
+        self.log_event("joined", &fuzzer_id, &registration.metric_endpoint)
+    }
+
+    fn driver_left(&mut self, fuzzer_id: &str, reason: &str) -> Result<(), String> {
+        if !self.drivers.get(fuzzer_id).map(|d| d.is_remote()).unwrap_or(false) {
+            return Err(format!("{} is not a remote driver", fuzzer_id));
+        }
+
+        self.drivers.remove(fuzzer_id);
+        self.batches.remove(fuzzer_id);
+        self.metric_reqs.remove(fuzzer_id);
+        self.remote_missed.remove(fuzzer_id);
+
+        info!("{} left ({})", fuzzer_id, reason);
+        self.log_event("left", fuzzer_id, reason)
+    }
+
+    // a batched fuzzer reached the end of a generation, from then on its driver is trusted over
+    // inotify to tell when
+    fn driver_ready(&mut self, ready: &Ready) {
+        match self.batches.get_mut(&ready.fuzzer_id) {
+            Some(batch) => {
+                debug!("{} ready (generation {})", ready.fuzzer_id, ready.generation);
+                batch.notified = true;
+                batch.ready = true;
+            },
+            None => warn!("ready message from {}, which is not batched", ready.fuzzer_id)
+        }
+    }
+
+    fn log_event(&mut self, event: &str, fuzzer_id: &str, details: &str) -> Result<(), String> {
+        let elapsed_time = self.elapsed_time();
+        self.dashboard.event(elapsed_time, event, fuzzer_id, details);
+        if let Some(ref mut file) = self.events_log_file {
+            let line = format!("{}{sep}{}{sep}{}{sep}{}\n", elapsed_time.num_milliseconds(),
+                event, fuzzer_id, details, sep=LOG_LINE_SEPARATOR);
+            file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    fn pull_driver_message(&mut self) -> Result<Option<DriverMessage>, String> {
+        let frames = match self.interesting_pull.as_ref().unwrap().recv_multipart(zmq::DONTWAIT) {
+            Ok(frames) => frames,
+            Err(zmq::Error::EAGAIN) => return Ok(None),
+            Err(error) => return Err(error.to_string())
+        };
+
+        match DriverMessage::from_frames(&frames)? {
+            DriverMessage::Interesting(interesting) => {
+                Ok(self.store_inline_data(interesting)?.map(DriverMessage::Interesting))
+            },
+            message => Ok(Some(message))
+        }
+    }
+
+    // keep the contents sent along with an interesting input in the blob store and refer to them
+    // from there, inputs that were already stored, are too large or come without their contents
+    // are dropped
+    fn store_inline_data(&mut self, mut interesting_input: InterestingInput)
+        -> Result<Option<InterestingInput>, String>
+    {
+        let blob_store = match (self.blob_store.as_ref(), interesting_input.data.is_some()) {
+            (Some(blob_store), true) => blob_store,
+            (Some(_), false) => {
+                // the paths of the input would be taken for blobs
+                warn!("dropping {} from {}, sent without its contents in inline mode",
+                    interesting_input.input_path, interesting_input.fuzzer_id);
+                return Ok(None);
+            },
+            (None, true) => {
+                warn!("ignoring contents sent by {}, inline mode is off",
+                    interesting_input.fuzzer_id);
+                interesting_input.data = None;
+                return Ok(Some(interesting_input));
+            },
+            (None, false) => return Ok(Some(interesting_input))
+        };
+        let data = interesting_input.data.take().unwrap();
+
+        if data.input.len() > blob_store.limit() || data.coverage.len() > blob_store.limit() {
+            warn!("dropping {} from {}, larger than the inline limit of {} bytes",
+                interesting_input.input_path, interesting_input.fuzzer_id, blob_store.limit());
+            return Ok(None);
+        }
+
+        // the coverage is read on every input from now on, it has to be made of branches
+        let stray_bytes = data.coverage.len() % mem::size_of::<Branch>();
+        if stray_bytes != 0 {
+            warn!("dropping {} from {}, coverage of {} bytes is not made of branches",
+                interesting_input.input_path, interesting_input.fuzzer_id, data.coverage.len());
+            return Ok(None);
+        }
+
+        let (input_path, new_input) = blob_store.store(&data.input, "input")?;
+        if !new_input {
+            info!("dropping {} from {}, same contents as {}", interesting_input.input_path,
+                interesting_input.fuzzer_id, input_path);
+            return Ok(None);
+        }
+
+        interesting_input.input_path = input_path;
+        interesting_input.coverage_path = blob_store.store(&data.coverage, "coverage")?.0;
+        Ok(Some(interesting_input))
+    }
+
+    // the contents travelling with an input in inline mode
+    fn load_inline_data(&self, interesting_input: &InterestingInput)
+        -> Result<Option<InputData>, String>
+    {
+        match self.blob_store {
+            Some(ref blob_store) => Ok(Some(InputData {
+                input: blob_store.load(&interesting_input.input_path)?,
+                coverage: blob_store.load(&interesting_input.coverage_path)?
+            })),
+            None => Ok(None)
+        }
+    }
+
+    fn driver_hello(&mut self, hello: &Hello) -> Result<(), String> {
+        let is_remote = match self.drivers.get_mut(&hello.fuzzer_id) {
+            Some(driver) => {
+                if !driver.is_remote() {
+                    driver.set_metric_endpoint(
+                        self.transport.connect_endpoint(&hello.metric_endpoint));
+                }
+                driver.is_remote()
+            },
+            None => {
+                warn!("hello from unknown driver {}", hello.fuzzer_id);
+                return Ok(());
+            }
+        };
+
+        // the metric server of drivers running elsewhere is known from their registration, the
+        // inputs of secondary instances are evaluated by the fuzzer they belong to
+        if !is_remote && !self.group_leaders.contains_key(&hello.fuzzer_id) {
+            self.connect_metric_socket(&hello.fuzzer_id)?;
+        }
+
+        info!("{} connected ({}, metric server {})", hello.fuzzer_id,
+            messages::PROTOCOL_VERSION, hello.metric_endpoint);
+        self.log_event("hello", &hello.fuzzer_id, messages::PROTOCOL_VERSION)
+    }
+
+    fn log_interesting(&mut self, interesting_input: &InterestingInput, signature: Option<u64>)
         -> Result<(), String>
     {
         let interesting_with_time = InterestingWithTime {
             input_message: interesting_input.clone(),
-            elapsed_time: self.start_time.unwrap().to(PreciseTime::now())
+            elapsed_time: self.elapsed_time()
         };
 
         if let Some(ref mut file) = self.interesting_log_file {
@@ -471,174 +1558,373 @@ impl Master {
             file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
         }
 
+        let participant = self.participant(&interesting_input.fuzzer_id).to_string();
+        self.winning_strategy.interesting_reported(&participant, signature,
+            interesting_with_time.elapsed_time);
+        self.last_interesting = interesting_with_time.elapsed_time;
+
         self.interesting_log.push(interesting_with_time);
 
         Ok(())
     }
 
-    fn get_high_strategy(&self) -> bool {
-        match self.winning_strategy {
-            WinningStrategy::SingleWinner(highest) => highest,
-            WinningStrategy::MultipleWinners(_, higher) => higher
+    // inputs not evaluated for each fuzzer so far, as time fuzzer_id duplicates
+    fn log_duplicates(&mut self) -> Result<(), String> {
+        let elapsed_time = self.elapsed_time();
+        if let Some(ref mut file) = self.dedup_log_file {
+            let mut duplicates: Vec<_> = self.duplicates.iter().collect();
+            duplicates.sort();
+            for (fuzzer_id, count) in duplicates {
+                let line = format!("{}{sep}{}{sep}{}\n", elapsed_time.num_milliseconds(),
+                    fuzzer_id, count, sep=LOG_LINE_SEPARATOR);
+                file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+            }
         }
+
+        Ok(())
     }
 
-    fn process_interesting<'a, W>(&mut self, interesting_input: InterestingInput, watch_data: W,
-        best_interesting: &mut BestInterestingMap)
+    fn log_strategy_state(&mut self) -> Result<(), String> {
+        let elapsed_time = self.elapsed_time();
+        if let Some(ref mut file) = self.strategy_log_file {
+            for state_line in self.winning_strategy.dump_state() {
+                let line = format!("{}{sep}{}\n", elapsed_time.num_milliseconds(), state_line,
+                    sep=LOG_LINE_SEPARATOR);
+                file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_interesting(&mut self, interesting_input: InterestingInput, signature: Option<u64>)
         -> Result<(), String>
-        where W: Iterator<Item=&'a WatchDescriptorData>
     {
-        let start_processing_duration = self.start_time.unwrap().to(PreciseTime::now());
+        let start_processing_duration = self.elapsed_time();
+
+        // fuzzers that found or were given an input with the same coverage already do not get
+        // to evaluate this one
+        let sender = self.participant(&interesting_input.fuzzer_id).to_string();
+        let duplicate_for: HashSet<String> = match signature {
+            Some(signature) => self.known_signatures.iter()
+                .filter(|t| *t.0 != sender && t.1.contains(&signature))
+                .map(|t| t.0.clone()).collect(),
+            None => HashSet::new()
+        };
+        for fuzzer_id in &duplicate_for {
+            *self.duplicates.entry(fuzzer_id.clone()).or_insert(0) += 1;
+        }
+        if let Some(signature) = signature {
+            self.known_signatures.entry(sender.clone()).or_default().insert(signature);
+        }
 
-        let metrics = self.evaluate_interesting(&interesting_input)?;
+        let metrics = self.evaluate_interesting(&interesting_input, &duplicate_for)?;
 
-        // update best_interesting table
-        for (fuzzer_id, metric_rep) in &metrics {
-            if metric_rep.metric == 0.0 { continue; }
-            if let Some(best_vec) = best_interesting.get_mut(fuzzer_id) {
-                best_vec.push((interesting_input.clone(), metric_rep.clone()));
-            }
+        // collect it for batched fuzzers, which get the best ones once ready
+        let batched_ids: Vec<_> = metrics.iter()
+            .filter(|t| t.1.metric != 0.0 && self.batches.contains_key(t.0))
+            .map(|t| t.0.clone()).collect();
+        for fuzzer_id in &batched_ids {
+            self.batches.get_mut(fuzzer_id).unwrap().add(interesting_input.clone(),
+                metrics[fuzzer_id].clone(), signature, start_processing_duration);
         }
 
-        // if from VUzzer, broadcast it if metric is not zero
-        let winning_drivers = if self.drivers.get(&interesting_input.fuzzer_id).unwrap().is_vuzzer() {
+        // if from a batched fuzzer, broadcast it if metric is not zero
+        let from_batched = self.batches.contains_key(&sender);
+        let winning_drivers = if from_batched {
             self.drivers.keys().filter_map(|f| {
                 // if the sender is not f, then check metric value (metric must exist for f)
-                if *f == interesting_input.fuzzer_id || metrics.get(f).unwrap().metric == 0.0 { None }
+                if *f == sender ||
+                    metrics.get(f).map(|m| m.metric == 0.0).unwrap_or(true) { None }
                 else { Some(f.to_owned()) }
             }).collect()
         } else {
             let mut metrics_c = metrics.clone();
-            for wdata in watch_data {
-                metrics_c.remove(&wdata.fuzzer_id);
+            for fuzzer_id in self.batches.keys() {
+                metrics_c.remove(fuzzer_id);
             }
-            self.metric_winners(&metrics_c)?
+            self.metric_winners(&interesting_input, &metrics_c)?
         };
 
         if !winning_drivers.is_empty() {
-            self.assign_input(&interesting_input, &winning_drivers)?;
-
-            // log competition
-            if let Some(ref mut file) = self.winning_log_file {
-                let mut winning_drivers_sort = winning_drivers.clone();
-                winning_drivers_sort.sort();
-                let line = format!("{}{sep}{}{sep}{}\n",
-                    start_processing_duration.num_milliseconds(), interesting_input.fuzzer_id,
-                    winning_drivers_sort.join("_"), sep=LOG_LINE_SEPARATOR);
-                file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
-            }
+            self.deliver(&interesting_input, &winning_drivers, start_processing_duration,
+                signature)?;
         }
 
-        println!("{:02}:{:02}:{:02} - {} - {} - {}",
-            start_processing_duration.num_hours(),
-            start_processing_duration.num_minutes() % 60,
-            start_processing_duration.num_seconds() % 60,
-            interesting_input.fuzzer_id,
-            metrics.iter().map(|t| format!("{} {}", t.0, t.1.metric))
-             .collect::<Vec<_>>().join(" / "),
-            if winning_drivers.len() > 0 { winning_drivers.join(" ") }
-            else { "none".to_string() });
+        for (fuzzer_id, metric_rep) in &metrics {
+            self.dashboard.metric(fuzzer_id, metric_rep.metric);
+        }
+        // inputs collected for a batch are only injected (and counted as such) once it is taken
+        for fuzzer_id in &winning_drivers {
+            self.dashboard.won(fuzzer_id);
+        }
+        if !self.dashboard.is_enabled() {
+            println!("{:02}:{:02}:{:02} - {} - {} - {}",
+                start_processing_duration.num_hours(),
+                start_processing_duration.num_minutes() % 60,
+                start_processing_duration.num_seconds() % 60,
+                interesting_input.fuzzer_id,
+                metrics.iter().map(|t| format!("{} {}", t.0, t.1))
+                 .collect::<Vec<_>>().join(" / "),
+                if !winning_drivers.is_empty() { winning_drivers.join(" ") }
+                else { "none".to_string() });
+        }
 
         Ok(())
     }
 
-    fn evaluate_interesting(&self, interesting_input: &InterestingInput)
-        -> Result<HashMap<String, RepMetric>, String>
+    // bind a socket, returning the endpoint it ended up bound to (with the port picked by the
+    // system if the address had none)
+    fn bind_socket(&self, socket_type: zmq::SocketType, name: &str, address: &str)
+        -> Result<(zmq::Socket, String), String>
     {
-        let mut metrics = HashMap::new();
-        let request = ReqMetric { coverage_path: interesting_input.coverage_path.clone() };
-        for (fuzzer_id, metric_socket) in &self.metric_reqs {
-            if *fuzzer_id == interesting_input.fuzzer_id {
-                continue;
-            }
+        let socket = self.context.socket(socket_type).map_err(|e| {
+            format!("failed to create {} socket: {}", name, e)
+        })?;
 
-            metric_socket.send_str(&request.to_string(), 0).map_err(|e| {
-                format!("error sending metric req to {}: {}", fuzzer_id, e)
-            })?;
+        socket.bind(address).map_err(|e| match e {
+            zmq::Error::EADDRINUSE => format!("failed to bind {} socket to {}: port already in \
+                use (by another campaign?), choose another one or leave it out of the campaign \
+                file to have one picked", name, address),
+            e => format!("failed to bind {} socket to {}: {}", name, address, e)
+        })?;
 
-            let rep_bytes = metric_socket.recv_bytes(0).map_err(|e| {
-                format!("error receiving metric rep from {}: {}", fuzzer_id, e)
-            })?;
+        let endpoint = socket.get_last_endpoint()
+            .map_err(|e| format!("failed to get {} socket endpoint: {}", name, e))?
+            .map_err(|_| format!("invalid {} socket endpoint", name))?;
 
-            let rep = String::from_utf8_lossy(&rep_bytes).parse().map_err(|e| {
-                format!("error parsing metric rep from {}: {}", fuzzer_id, e)
-            })?;
+        info!("bind '{}' socket {}", name, endpoint);
+        Ok((socket, endpoint))
+    }
 
-            metrics.insert(fuzzer_id.clone(), rep);
-        }
+    // the fuzzer taking part in the campaign for a driver, itself unless it is a secondary
+    // instance
+    fn participant<'a>(&'a self, fuzzer_id: &'a str) -> &'a str {
+        self.group_leaders.get(fuzzer_id).map(|l| l.as_str()).unwrap_or(fuzzer_id)
+    }
 
-        Ok(metrics)
+    fn connect_metric_socket(&mut self, fuzzer_id: &str) -> Result<(), String> {
+        let socket = self.context.socket(zmq::REQ).map_err(|e| {
+            format!("failed to create metric socket for {}: {}", fuzzer_id, e)
+        })?;
+        // do not hold on to pending requests of a socket that gets replaced
+        socket.set_linger(0).map_err(|e| {
+            format!("failed to set linger on metric socket for {}: {}", fuzzer_id, e)
+        })?;
+
+        let address = self.drivers[fuzzer_id].get_metric_endpoint()
+            .ok_or(format!("metric server of {} is not known yet", fuzzer_id))?;
+        socket.connect(address).map_err(|e| {
+            format!("failed to connect to metric socket {}: {}", address, e)
+        })?;
+
+        self.metric_reqs.insert(fuzzer_id.to_string(), socket);
+        Ok(())
     }
 
-    fn metric_winners(&self, metrics: &HashMap<String, RepMetric>)
-        -> Result<Vec<String>, String>
+    // the metrics of the input for every driver, but the one it comes from and the ones in `skip`
+    fn evaluate_interesting(&mut self, interesting_input: &InterestingInput,
+        skip: &HashSet<String>) -> Result<HashMap<String, RepMetric>, String>
     {
-        let winning_drivers = match self.winning_strategy {
-            WinningStrategy::SingleWinner(highest) => {
-                if let Some(w) = Master::metric_single_winner(&metrics, highest)? {
-                    vec![w]
-                } else {
-                    vec![]
+        if self.local_coverage.is_some() {
+            return Ok(self.evaluate_locally(interesting_input, skip));
+        }
+
+        let coverage_data = match self.blob_store {
+            Some(ref blob_store) => match blob_store.load(&interesting_input.coverage_path) {
+                Ok(coverage_data) => Some(coverage_data),
+                Err(e) => {
+                    warn!("not evaluating {}: {}", interesting_input.input_path, e);
+                    return Ok(HashMap::new());
                 }
             },
-            WinningStrategy::MultipleWinners(threshold, higher) => {
-                Master::metric_multiple_winners(&metrics, threshold, higher)?
-            }
+            None => None
         };
+        let request = ReqMetric {
+            metrics: self.metrics.names().to_vec(),
+            input_size: input_size(interesting_input),
+            coverage_path: interesting_input.coverage_path.clone(),
+            coverage_data
+        };
+        let request_frames = request.to_frames();
 
-        Ok(winning_drivers)
-    }
+        // fan out the request to every driver, but the ones the input comes from
+        let mut pending = vec![];
+        let sender = self.participant(&interesting_input.fuzzer_id).to_string();
+        for (fuzzer_id, metric_socket) in &self.metric_reqs {
+            if *fuzzer_id == sender || skip.contains(fuzzer_id) {
+                continue;
+            }
 
-    fn metric_single_winner(metrics: &HashMap<String, RepMetric>, highest: bool)
-        -> Result<Option<String>, String>
-    {
-        if metrics.iter().all(|tpl| tpl.1.metric == 0.0) {
-            return Ok(None);
+            // a driver that went away would block the send forever
+            let send_res = metric_socket.send_multipart(&messages::frame_slices(&request_frames),
+                zmq::DONTWAIT);
+            match send_res {
+                Ok(()) => pending.push(fuzzer_id.clone()),
+                Err(zmq::Error::EAGAIN) => warn!("{} is not accepting metric requests", fuzzer_id),
+                Err(e) => return Err(format!("error sending metric req to {}: {}", fuzzer_id, e))
+            }
         }
 
-        // shuffling is done so that in cases where the metrics are all equal a different one gets
-        // picked each time
-        let mut metrics_vec: Vec<_> = metrics.iter().collect();
-        thread_rng().shuffle(metrics_vec.as_mut_slice());
+        // collect replies in whatever order they come until the timeout expires
+        let mut metrics = HashMap::new();
+        let request_time = PreciseTime::now();
+        while !pending.is_empty() {
+            let remaining = self.metric_timeout - request_time.to(PreciseTime::now());
+            if remaining <= Duration::zero() {
+                break;
+            }
 
-        let mut iter = metrics_vec.iter();
-        let (mut winning_key, mut winning_val) = *iter.next()
-            .ok_or("metrics hashmap is empty".to_string())?;
+            let readable: Vec<bool> = {
+                let mut poll_items: Vec<_> = pending.iter().map(|f| {
+                    self.metric_reqs[f].as_poll_item(zmq::POLLIN)
+                }).collect();
+                zmq::poll(&mut poll_items, remaining.num_milliseconds()).map_err(|e| {
+                    format!("error polling metric sockets: {}", e)
+                })?;
+                poll_items.iter().map(|i| i.is_readable()).collect()
+            };
 
-        for tpl in iter {
-            let (k, v) = *tpl;
-            if (highest && v.metric > winning_val.metric) ||
-                (!highest && v.metric < winning_val.metric)
-            {
-                winning_val = v;
-                winning_key = k;
+            let mut still_pending = vec![];
+            for (fuzzer_id, is_readable) in pending.into_iter().zip(readable) {
+                if !is_readable {
+                    still_pending.push(fuzzer_id);
+                    continue;
+                }
+
+                let rep_frames = self.metric_reqs[&fuzzer_id].recv_multipart(0).map_err(|e| {
+                    format!("error receiving metric rep from {}: {}", fuzzer_id, e)
+                })?;
+
+                // drivers replying with an error go on serving the next requests
+                self.remote_missed.remove(&fuzzer_id);
+                match RepMetric::from_frames(&rep_frames, self.metrics.weights()) {
+                    Ok(rep) => { metrics.insert(fuzzer_id, rep); },
+                    Err(e) => warn!("no metric from {} for this input: {}", fuzzer_id, e)
+                }
             }
+            pending = still_pending;
         }
 
-        Ok(Some(winning_key.clone()))
+        // drivers that did not reply in time have no metric for this input, and their REQ socket
+        // (stuck waiting for the reply) is replaced by a fresh one. Remote drivers not replying
+        // repeatedly are considered gone, as the master cannot tell otherwise.
+        for fuzzer_id in pending {
+            warn!("{} did not reply to metric request for {} within {}ms", fuzzer_id,
+                interesting_input.input_path, self.metric_timeout.num_milliseconds());
+
+            if self.drivers[&fuzzer_id].is_remote() {
+                let missed = {
+                    let missed = self.remote_missed.entry(fuzzer_id.clone()).or_insert(0);
+                    *missed += 1;
+                    *missed
+                };
+                if missed >= REMOTE_MAX_MISSED_METRICS {
+                    self.driver_left(&fuzzer_id, "unresponsive")?;
+                    continue;
+                }
+            }
+
+            self.connect_metric_socket(&fuzzer_id)?;
+        }
+
+        Ok(metrics)
     }
 
-    fn metric_multiple_winners(metrics: &HashMap<String, RepMetric>, threshold: f64, higher: bool)
+    fn metric_winners(&mut self, interesting_input: &InterestingInput,
+        metrics: &HashMap<String, RepMetric>)
         -> Result<Vec<String>, String>
     {
-        let mut winners = vec![];
+        let state = CampaignState { interesting_input };
 
-        for (k, v) in metrics {
-            if (higher && v.metric > threshold) || (!higher && v.metric < threshold) {
-                winners.push(k.clone());
+        self.winning_strategy.winners(metrics, &state)
+    }
+
+    // the metric of every connected driver, but the ones the input comes from, as drivers would
+    // reply to metric requests
+    fn evaluate_locally(&self, interesting_input: &InterestingInput, skip: &HashSet<String>)
+        -> HashMap<String, RepMetric>
+    {
+        let local_coverage = self.local_coverage.as_ref().unwrap();
+        let branches: Vec<Branch> = match read_structs(&interesting_input.coverage_path) {
+            Ok(branches) => branches,
+            Err(e) => {
+                warn!("failed to read coverage from {}: {}", interesting_input.coverage_path, e);
+                return HashMap::new();
+            }
+        };
+
+        let hits = metric::count_hits(&branches);
+        let input_size = input_size(interesting_input);
+
+        let sender = self.participant(&interesting_input.fuzzer_id);
+        self.metric_reqs.keys().filter(|f| *f != sender && !skip.contains(*f)).map(|f| {
+            (f.clone(), local_coverage.scores(f, &hits, input_size, &self.metrics))
+        }).collect()
+    }
+
+    // send an input to the fuzzers it won, streaming or batched alike, and log the competition
+    fn deliver(&mut self, interesting_input: &InterestingInput, fuzzer_ids: &[String],
+        elapsed_time: Duration, signature: Option<u64>) -> Result<(), String>
+    {
+        // a blob gone missing from the store costs the input, not the campaign
+        let input = match self.load_inline_data(interesting_input) {
+            Ok(data) => InterestingInput { data, ..interesting_input.clone() },
+            Err(e) => {
+                warn!("not delivering {}: {}", interesting_input.input_path, e);
+                return Ok(());
+            }
+        };
+        self.assign_input(&input, fuzzer_ids, signature)?;
+
+        if let Some(signature) = signature {
+            for fuzzer_id in fuzzer_ids {
+                self.known_signatures.entry(fuzzer_id.clone()).or_default()
+                    .insert(signature);
             }
         }
 
-        Ok(winners)
+        if let Some(ref mut file) = self.winning_log_file {
+            let mut fuzzer_ids_sort = fuzzer_ids.to_vec();
+            fuzzer_ids_sort.sort();
+            let line = format!("{}{sep}{}{sep}{}\n", elapsed_time.num_milliseconds(),
+                interesting_input.fuzzer_id, fuzzer_ids_sort.join(WINNERS_SEPARATOR),
+                sep=LOG_LINE_SEPARATOR);
+            file.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+        }
 
+        Ok(())
     }
 
-    fn assign_input(&self, interesting_input: &InterestingInput, fuzzer_ids: &[String])
-        -> Result<(), String>
+    fn assign_input(&mut self, interesting_input: &InterestingInput, fuzzer_ids: &[String],
+        signature: Option<u64>) -> Result<(), String>
     {
-        let input = interesting_input.use_for(fuzzer_ids);
-        self.use_pub.as_ref().unwrap().send_str(&input.to_string(), 0).map_err(|e| {
+        let input_frames = interesting_input.use_for(fuzzer_ids).to_frames();
+        let use_pub = self.use_pub.as_ref().unwrap();
+        use_pub.send_multipart(&messages::frame_slices(&input_frames), 0).map_err(|e| {
             format!("error publishing input to use: {}", e)
-        })
+        })?;
+
+        // drivers add the coverage of the inputs they use to the one they know of
+        if let Some(ref mut local_coverage) = self.local_coverage {
+            match read_structs::<Branch, _>(&interesting_input.coverage_path) {
+                Ok(branches) => {
+                    let hits = metric::count_hits(&branches);
+                    for fuzzer_id in fuzzer_ids {
+                        local_coverage.given(fuzzer_id, &hits);
+                    }
+                },
+                Err(e) => warn!("failed to read coverage from {}: {}",
+                    interesting_input.coverage_path, e)
+            }
+        }
+
+        let elapsed_time = self.elapsed_time();
+        self.winning_strategy.inputs_assigned(fuzzer_ids, signature, elapsed_time);
+        for fuzzer_id in fuzzer_ids {
+            *self.won_counts.entry(fuzzer_id.clone()).or_insert(0) += 1;
+        }
+
+        Ok(())
     }
 }
@@ -1,12 +1,148 @@
-use std::str::FromStr;
+use std::fmt;
+use std::str;
+
+// messages exchanged with drivers, each one is a multipart zmq message made of the protocol
+// version, the message kind and the message fields (one per frame). In inline mode the contents
+// of inputs and coverage files travel in additional frames.
+
+pub const PROTOCOL_VERSION: &str = "uberfuzz/6";
+// published messages are prefixed by the subscription topic, by subscribing to it drivers can
+// receive all messages
+pub const USE_TOPIC: &str = "A";
+
+const HELLO_KIND: &str = "hello";
+const INTERESTING_KIND: &str = "interesting";
+const READY_KIND: &str = "ready";
+const USE_KIND: &str = "use";
+const USE_DATA_KIND: &str = "use-data";
+const METRIC_KIND: &str = "metric";
+const ERROR_KIND: &str = "error";
+const REGISTER_KIND: &str = "register";
+const UNREGISTER_KIND: &str = "unregister";
+const REGISTERED_KIND: &str = "registered";
+
+
+pub type Frames = Vec<Vec<u8>>;
+
+// borrow frames as zmq::Socket::send_multipart wants them
+pub fn frame_slices(frames: &Frames) -> Vec<&[u8]> {
+    frames.iter().map(|f| f.as_slice()).collect()
+}
+
+// check the protocol version, returning the message kind and the frames following it
+fn parse_header(frames: &[Vec<u8>]) -> Result<(&str, &[Vec<u8>]), String> {
+    let version = frames.first().map(|f| String::from_utf8_lossy(f))
+        .ok_or("empty message".to_string())?;
+    if version != PROTOCOL_VERSION {
+        return Err(format!("protocol version mismatch: got '{}', expected '{}'", version,
+            PROTOCOL_VERSION));
+    }
+
+    let kind = frames.get(1).ok_or("message without kind".to_string())?;
+    let kind = str::from_utf8(kind).map_err(|e| format!("invalid message kind: {}", e))?;
+
+    Ok((kind, &frames[2..]))
+}
+
+fn parse_fields(kind: &str, frames: &[Vec<u8>], names: &[&str]) -> Result<Vec<String>, String> {
+    if frames.len() != names.len() {
+        return Err(format!("{} message has {} fields instead of {} ({})", kind, frames.len(),
+            names.len(), names.join(", ")));
+    }
+
+    frames.iter().zip(names).map(|(frame, name)| {
+        String::from_utf8(frame.clone()).map_err(|e| {
+            format!("invalid {} in {} message: {}", name, kind, e)
+        })
+    }).collect()
+}
+
+
+// what drivers push to the master
+#[derive(Debug)]
+pub enum DriverMessage {
+    Hello(Hello),
+    Interesting(InterestingInput),
+    Ready(Ready)
+}
+
+impl DriverMessage {
+    pub fn from_frames(frames: &[Vec<u8>]) -> Result<DriverMessage, String> {
+        let (kind, fields) = parse_header(frames)?;
+        match kind {
+            HELLO_KIND => {
+                let fields = parse_fields(kind, fields, &["fuzzer_id", "metric_endpoint"])?;
+                Ok(DriverMessage::Hello(Hello {
+                    fuzzer_id: fields[0].clone(),
+                    metric_endpoint: fields[1].clone()
+                }))
+            },
+            INTERESTING_KIND => {
+                // the contents follow the paths in inline mode
+                let (fields, data) = if fields.len() == 5 {
+                    let data = InputData { input: fields[3].clone(), coverage: fields[4].clone() };
+                    (&fields[..3], Some(data))
+                } else {
+                    (fields, None)
+                };
+
+                let fields = parse_fields(kind, fields,
+                    &["fuzzer_id", "input_path", "coverage_path"])?;
+                Ok(DriverMessage::Interesting(InterestingInput {
+                    fuzzer_id: fields[0].clone(),
+                    input_path: fields[1].clone(),
+                    coverage_path: fields[2].clone(),
+                    data
+                }))
+            },
+            READY_KIND => {
+                let fields = parse_fields(kind, fields, &["fuzzer_id", "generation"])?;
+                let generation = fields[1].parse().map_err(|e| {
+                    format!("invalid generation {} in ready message: {}", fields[1], e)
+                })?;
+                Ok(DriverMessage::Ready(Ready {
+                    fuzzer_id: fields[0].clone(),
+                    generation
+                }))
+            },
+            _ => Err(format!("unexpected {} message from driver", kind))
+        }
+    }
+}
+
+
+// sent by drivers once connected, so that protocol mismatches show up early, along with the
+// endpoint their metric server is bound to
+#[derive(Debug)]
+pub struct Hello {
+    pub fuzzer_id: String,
+    pub metric_endpoint: String
+}
+
+
+// sent by drivers of batched fuzzers when a generation ends, the fuzzer being ready for the
+// inputs collected meanwhile
+#[derive(Debug)]
+pub struct Ready {
+    pub fuzzer_id: String,
+    pub generation: u64
+}
+
+
+// contents of an input and of its coverage file, for drivers not sharing the master's filesystem
+#[derive(Debug, Clone)]
+pub struct InputData {
+    pub input: Vec<u8>,
+    pub coverage: Vec<u8>
+}
 
-// messages exchanged with drivers
 
 #[derive(Debug, Clone)]
 pub struct InterestingInput {
     pub fuzzer_id: String,
     pub input_path: String,
-    pub coverage_path: String
+    pub coverage_path: String,
+    pub data: Option<InputData>
 }
 
 impl InterestingInput {
@@ -14,72 +150,341 @@ impl InterestingInput {
         UseInput {
             fuzzer_ids: fuzzer_ids.iter().map(|f| f.to_string()).collect(),
             input_path: self.input_path.clone(),
-            coverage_path: self.coverage_path.clone()
+            coverage_path: self.coverage_path.clone(),
+            data: self.data.clone()
         }
     }
 }
 
-impl FromStr for InterestingInput {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut splitted = s.split(" ");
-
-        let mut parse_next = |field_name| {
-            splitted.next().ok_or(format!("unable to parse {} from '{}'", field_name, s))
-        };
-
-        let fuzzer_id = parse_next("fuzzer_id")?;
-        let input_path = parse_next("input_path")?;
-        let coverage_path = parse_next("coverage_path")?;
 
-        let interesting_input = InterestingInput {
-            fuzzer_id: fuzzer_id.to_string().clone(),
-            input_path: input_path.to_string().clone(),
-            coverage_path: coverage_path.to_string().clone()
-        };
+#[derive(Debug)]
+pub struct UseInput {
+    fuzzer_ids: Vec<String>,
+    input_path: String,
+    coverage_path: String,
+    data: Option<InputData>
+}
 
-        Ok(interesting_input)
+impl UseInput {
+    pub fn to_frames(&self) -> Frames {
+        // the receivers come last as there can be any number of them
+        let kind = if self.data.is_some() { USE_DATA_KIND } else { USE_KIND };
+        let mut frames: Frames = [USE_TOPIC, PROTOCOL_VERSION, kind, &self.input_path,
+            &self.coverage_path].iter().map(|f| f.as_bytes().to_vec()).collect();
+        if let Some(ref data) = self.data {
+            frames.push(data.input.clone());
+            frames.push(data.coverage.clone());
+        }
+        frames.extend(self.fuzzer_ids.iter().map(|f| f.as_bytes().to_vec()));
+        frames
     }
 }
 
 
 #[derive(Debug)]
-pub struct UseInput {
-    fuzzer_ids: Vec<String>,
-    input_path: String,
-    coverage_path: String
+pub struct ReqMetric {
+    // names of the metrics to compute
+    pub metrics: Vec<String>,
+    pub input_size: u64,
+    pub coverage_path: String,
+    pub coverage_data: Option<Vec<u8>>
 }
 
-impl ToString for UseInput {
-    fn to_string(&self) -> String {
-        // the 'A' is the subscription topic, by subscribing to it drivers can receive all messages
-        format!("A {} {} {}", self.fuzzer_ids.join("_"), self.input_path, self.coverage_path)
+impl ReqMetric {
+    pub fn to_frames(&self) -> Frames {
+        let metrics = self.metrics.join(",");
+        let input_size = self.input_size.to_string();
+        let mut frames: Frames = [PROTOCOL_VERSION, METRIC_KIND, &metrics, &input_size,
+            &self.coverage_path].iter().map(|f| f.as_bytes().to_vec()).collect();
+        if let Some(ref coverage_data) = self.coverage_data {
+            frames.push(coverage_data.clone());
+        }
+        frames
     }
 }
 
 
+// what drivers running elsewhere send to join or leave a campaign
 #[derive(Debug)]
-pub struct ReqMetric {
-    pub coverage_path: String
+pub enum RegistrationMessage {
+    Register(Registration),
+    Unregister(String)
+}
+
+impl RegistrationMessage {
+    pub fn from_frames(frames: &[Vec<u8>]) -> Result<RegistrationMessage, String> {
+        let (kind, fields) = parse_header(frames)?;
+        match kind {
+            REGISTER_KIND => {
+                let fields = parse_fields(kind, fields,
+                    &["fuzzer_id", "fuzzer_type", "metric_endpoint"])?;
+                Ok(RegistrationMessage::Register(Registration {
+                    fuzzer_id: fields[0].clone(),
+                    fuzzer_type: fields[1].clone(),
+                    metric_endpoint: fields[2].clone()
+                }))
+            },
+            UNREGISTER_KIND => {
+                let fields = parse_fields(kind, fields, &["fuzzer_id"])?;
+                Ok(RegistrationMessage::Unregister(fields[0].clone()))
+            },
+            _ => Err(format!("unexpected {} message on registration socket", kind))
+        }
+    }
+
+    // the reply to a registration message, with the reason of a failure
+    pub fn reply_frames(result: &Result<(), String>) -> Frames {
+        let frames = match *result {
+            Ok(()) => vec![PROTOCOL_VERSION, REGISTERED_KIND],
+            Err(ref e) => vec![PROTOCOL_VERSION, ERROR_KIND, e]
+        };
+        frames.iter().map(|f| f.as_bytes().to_vec()).collect()
+    }
 }
 
-impl ToString for ReqMetric {
-    fn to_string(&self) -> String { self.coverage_path.clone() }
+
+#[derive(Debug)]
+pub struct Registration {
+    pub fuzzer_id: String,
+    pub fuzzer_type: String,
+    pub metric_endpoint: String
 }
 
 
+// the scores of the requested metrics and the metric strategies use, their weighted sum
 #[derive(Debug, Clone)]
 pub struct RepMetric {
-    pub metric: f64
+    pub metric: f64,
+    pub scores: Vec<f64>
+}
+
+impl RepMetric {
+    pub fn new(scores: Vec<f64>, weights: &[f64]) -> RepMetric {
+        let metric = scores.iter().zip(weights).map(|(s, w)| s * w).sum();
+        RepMetric { metric, scores }
+    }
+
+    // a score for each of the weights of the requested metrics
+    pub fn from_frames(frames: &[Vec<u8>], weights: &[f64]) -> Result<RepMetric, String> {
+        let (kind, fields) = parse_header(frames)?;
+        match kind {
+            METRIC_KIND => {
+                let fields = parse_fields(kind, fields, &vec!["score"; weights.len()])?;
+                let scores = fields.iter().map(|score| score.parse().map_err(|e| {
+                    format!("failed parsing metric {}. {}", score, e)
+                })).collect::<Result<Vec<f64>, String>>()?;
+                Ok(RepMetric::new(scores, weights))
+            },
+            // drivers reply with an error to requests they cannot understand
+            ERROR_KIND => {
+                let fields = parse_fields(kind, fields, &["error"])?;
+                Err(format!("driver error: {}", fields[0]))
+            },
+            _ => Err(format!("unexpected {} message in reply to metric request", kind))
+        }
+    }
+}
+
+// the metric, followed by the scores it combines if there are several
+impl fmt::Display for RepMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.metric)?;
+        if self.scores.len() > 1 {
+            let scores: Vec<_> = self.scores.iter().map(|s| s.to_string()).collect();
+            write!(f, " ({})", scores.join(" "))?;
+        }
+        Ok(())
+    }
 }
 
-impl FromStr for RepMetric {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let metric = s.parse().map_err(|e| {
-            format!("failed parsing metric {}. {}", s, e)
-        })?;
 
-        Ok(RepMetric { metric: metric })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(fields: &[&str]) -> Frames {
+        fields.iter().map(|f| f.as_bytes().to_vec()).collect()
+    }
+
+    fn interesting(frames: &[Vec<u8>]) -> InterestingInput {
+        match DriverMessage::from_frames(frames) {
+            Ok(DriverMessage::Interesting(interesting)) => interesting,
+            other => panic!("expected an interesting input, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn use_frames_follow_driver_layout() {
+        let input = InterestingInput {
+            fuzzer_id: "afl_main".to_string(),
+            input_path: "/work dir/queue/id 000001".to_string(),
+            coverage_path: "/work dir/coverage/id 000001.cov".to_string(),
+            data: None
+        };
+        let receivers = vec!["afl_secondary_1".to_string(), "vu_zzer".to_string()];
+
+        // topic, version, kind, input path, coverage path, receivers
+        assert_eq!(input.use_for(&receivers).to_frames(), frames(&[USE_TOPIC, PROTOCOL_VERSION,
+            "use", "/work dir/queue/id 000001", "/work dir/coverage/id 000001.cov",
+            "afl_secondary_1", "vu_zzer"]));
+    }
+
+    #[test]
+    fn use_frames_carry_data_before_receivers() {
+        let input = InterestingInput {
+            fuzzer_id: "afl_main".to_string(),
+            input_path: "in put".to_string(),
+            coverage_path: "cover age".to_string(),
+            data: Some(InputData { input: vec![0, 1, 2], coverage: vec![0xff; 16] })
+        };
+
+        let use_frames = input.use_for(&["hongg_fuzz".to_string()]).to_frames();
+        assert_eq!(use_frames.len(), 8);
+        assert_eq!(use_frames[2], b"use-data".to_vec());
+        assert_eq!(use_frames[3], b"in put".to_vec());
+        assert_eq!(use_frames[4], b"cover age".to_vec());
+        assert_eq!(use_frames[5], vec![0, 1, 2]);
+        assert_eq!(use_frames[6], vec![0xff; 16]);
+        assert_eq!(use_frames[7], b"hongg_fuzz".to_vec());
+    }
+
+    #[test]
+    fn metric_request_frames() {
+        let request = ReqMetric {
+            metrics: vec!["new-branches".to_string(), "rarity".to_string()],
+            input_size: 1024,
+            coverage_path: "/work dir/a b.cov".to_string(),
+            coverage_data: None
+        };
+        assert_eq!(request.to_frames(), frames(&[PROTOCOL_VERSION, "metric",
+            "new-branches,rarity", "1024", "/work dir/a b.cov"]));
+
+        let request = ReqMetric { coverage_data: Some(vec![7; 16]), ..request };
+        let request_frames = request.to_frames();
+        assert_eq!(request_frames.len(), 6);
+        assert_eq!(request_frames[5], vec![7; 16]);
+    }
+
+    #[test]
+    fn interesting_from_paths() {
+        let input = interesting(&frames(&[PROTOCOL_VERSION, "interesting", "afl_secondary_2",
+            "/work dir/queue/id 000002", "/work dir/id 000002.cov"]));
+        assert_eq!(input.fuzzer_id, "afl_secondary_2");
+        assert_eq!(input.input_path, "/work dir/queue/id 000002");
+        assert_eq!(input.coverage_path, "/work dir/id 000002.cov");
+        assert!(input.data.is_none());
+    }
+
+    #[test]
+    fn interesting_with_data() {
+        let mut message = frames(&[PROTOCOL_VERSION, "interesting", "vu_zzer", "in put",
+            "cover age"]);
+        message.push(vec![0, 159, 146, 150]);
+        message.push(vec![]);
+
+        let input = interesting(&message);
+        assert_eq!(input.fuzzer_id, "vu_zzer");
+        assert_eq!(input.input_path, "in put");
+        let data = input.data.unwrap();
+        assert_eq!(data.input, vec![0, 159, 146, 150]);
+        assert!(data.coverage.is_empty());
+    }
+
+    #[test]
+    fn interesting_with_missing_fields() {
+        let err = DriverMessage::from_frames(&frames(&[PROTOCOL_VERSION, "interesting",
+            "afl_main", "input"])).unwrap_err();
+        assert!(err.contains("interesting message has 2 fields instead of 3"), "{}", err);
+    }
+
+    #[test]
+    fn hello_and_ready() {
+        match DriverMessage::from_frames(&frames(&[PROTOCOL_VERSION, "hello", "afl_main",
+            "ipc:///work dir/sockets/metric_afl_main"]))
+        {
+            Ok(DriverMessage::Hello(hello)) => {
+                assert_eq!(hello.fuzzer_id, "afl_main");
+                assert_eq!(hello.metric_endpoint, "ipc:///work dir/sockets/metric_afl_main");
+            },
+            other => panic!("expected a hello, got {:?}", other)
+        }
+
+        match DriverMessage::from_frames(&frames(&[PROTOCOL_VERSION, "ready", "vu_zzer", "12"])) {
+            Ok(DriverMessage::Ready(ready)) => {
+                assert_eq!(ready.fuzzer_id, "vu_zzer");
+                assert_eq!(ready.generation, 12);
+            },
+            other => panic!("expected a ready, got {:?}", other)
+        }
+
+        let err = DriverMessage::from_frames(&frames(&[PROTOCOL_VERSION, "ready", "vu_zzer",
+            "twelve"])).unwrap_err();
+        assert!(err.contains("invalid generation twelve"), "{}", err);
+    }
+
+    #[test]
+    fn unexpected_driver_message() {
+        let err = DriverMessage::from_frames(&frames(&[PROTOCOL_VERSION, "use", "a", "b"]))
+            .unwrap_err();
+        assert_eq!(err, "unexpected use message from driver");
+    }
+
+    #[test]
+    fn registration() {
+        match RegistrationMessage::from_frames(&frames(&[PROTOCOL_VERSION, "register",
+            "vu_zzer_2", "vuzzer", "tcp://fuzz box:1337"]))
+        {
+            Ok(RegistrationMessage::Register(registration)) => {
+                assert_eq!(registration.fuzzer_id, "vu_zzer_2");
+                assert_eq!(registration.fuzzer_type, "vuzzer");
+                assert_eq!(registration.metric_endpoint, "tcp://fuzz box:1337");
+            },
+            other => panic!("expected a registration, got {:?}", other)
+        }
+
+        match RegistrationMessage::from_frames(&frames(&[PROTOCOL_VERSION, "unregister",
+            "vu_zzer_2"]))
+        {
+            Ok(RegistrationMessage::Unregister(fuzzer_id)) => assert_eq!(fuzzer_id, "vu_zzer_2"),
+            other => panic!("expected an unregistration, got {:?}", other)
+        }
+
+        assert_eq!(RegistrationMessage::reply_frames(&Ok(())),
+            frames(&[PROTOCOL_VERSION, "registered"]));
+        assert_eq!(RegistrationMessage::reply_frames(&Err("id taken".to_string())),
+            frames(&[PROTOCOL_VERSION, "error", "id taken"]));
+    }
+
+    #[test]
+    fn metric_reply() {
+        let reply = RepMetric::from_frames(&frames(&[PROTOCOL_VERSION, "metric", "3", "0.5"]),
+            &[1.0, 2.0]).unwrap();
+        assert_eq!(reply.scores, vec![3.0, 0.5]);
+        assert_eq!(reply.metric, 4.0);
+
+        let err = RepMetric::from_frames(&frames(&[PROTOCOL_VERSION, "metric", "3"]),
+            &[1.0, 2.0]).unwrap_err();
+        assert!(err.contains("metric message has 1 fields instead of 2"), "{}", err);
+
+        let err = RepMetric::from_frames(&frames(&[PROTOCOL_VERSION, "error",
+            "unknown metric rarity"]), &[1.0]).unwrap_err();
+        assert_eq!(err, "driver error: unknown metric rarity");
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let err = DriverMessage::from_frames(&frames(&["uberfuzz/5", "hello", "afl_main",
+            "tcp://localhost:1337"])).unwrap_err();
+        assert_eq!(err, format!("protocol version mismatch: got 'uberfuzz/5', expected '{}'",
+            PROTOCOL_VERSION));
+
+        // drivers predating versioning send the fuzzer id first
+        let err = RegistrationMessage::from_frames(&frames(&["afl_main", "register"]))
+            .unwrap_err();
+        assert!(err.starts_with("protocol version mismatch: got 'afl_main'"), "{}", err);
+
+        assert_eq!(DriverMessage::from_frames(&[]).unwrap_err(), "empty message");
+        assert_eq!(RepMetric::from_frames(&frames(&[PROTOCOL_VERSION]), &[]).unwrap_err(),
+            "message without kind");
     }
 }
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use coverage::Branch;
+use messages::RepMetric;
+
+
+pub const DEFAULT_METRIC: &str = "new-branches";
+// metrics drivers compute as well as the master
+const DRIVER_METRICS: &[&str] =
+    &["new-branches", "new-fraction", "hit-buckets", "size-penalty"];
+// metrics needing the coverage of the whole campaign, which only the master knows
+const CAMPAIGN_METRICS: &[&str] = &["rarity"];
+
+
+// the metrics inputs are evaluated with, combined into the one strategies use by a weighted sum
+#[derive(Debug)]
+pub struct Metrics {
+    names: Vec<String>,
+    weights: Vec<f64>
+}
+
+impl Metrics {
+    pub fn from_config(names: &[String], weights: Option<&Vec<f64>>, local: bool)
+        -> Result<Metrics, String>
+    {
+        let names = if names.is_empty() { vec![DEFAULT_METRIC.to_string()] }
+            else { names.to_vec() };
+
+        for name in &names {
+            let campaign_metric = CAMPAIGN_METRICS.contains(&name.as_str());
+            if !campaign_metric && !DRIVER_METRICS.contains(&name.as_str()) {
+                return Err(format!("unknown metric {} (one of {}, {})", name,
+                    DRIVER_METRICS.join(", "), CAMPAIGN_METRICS.join(", ")));
+            }
+            if campaign_metric && !local {
+                return Err(format!("metric {} needs metrics computed on the master", name));
+            }
+        }
+
+        let weights = match weights {
+            Some(weights) if weights.len() != names.len() => {
+                return Err(format!("{} metric weights given for {} metrics", weights.len(),
+                    names.len()));
+            },
+            Some(weights) => weights.clone(),
+            None => vec![1.0; names.len()]
+        };
+
+        Ok(Metrics { names, weights })
+    }
+
+    pub fn names(&self) -> &[String] { &self.names }
+
+    pub fn weights(&self) -> &[f64] { &self.weights }
+}
+
+
+// hit counts of the branches of an input, branches appear in coverage files once per hit
+pub fn count_hits(branches: &[Branch]) -> HashMap<Branch, u64> {
+    let mut hits = HashMap::new();
+    for branch in branches {
+        *hits.entry(*branch).or_insert(0) += 1;
+    }
+    hits
+}
+
+
+// the bucket of a hit count, as AFL defines them: 1, 2, 3, 4-7, 8-15, 16-31, 32-127, 128+
+fn hits_bucket(hits: u64) -> u8 {
+    match hits {
+        0..=3 => 1 << (hits.max(1) - 1),
+        4..=7 => 1 << 3,
+        8..=15 => 1 << 4,
+        16..=31 => 1 << 5,
+        32..=127 => 1 << 6,
+        _ => 1 << 7
+    }
+}
+
+
+// branches each fuzzer knows of (with the hit count buckets inputs hit them in), from the inputs
+// it found and the ones it was given, as its driver keeps them, so that the master computes
+// metrics without asking drivers
+pub struct CoverageTable {
+    known: HashMap<String, HashMap<Branch, u8>>,
+    // hits of the inputs found in the whole campaign
+    campaign_hits: HashMap<Branch, u64>
+}
+
+impl CoverageTable {
+    pub fn new() -> CoverageTable {
+        CoverageTable { known: HashMap::new(), campaign_hits: HashMap::new() }
+    }
+
+    // an input the fuzzer found
+    pub fn found(&mut self, fuzzer_id: &str, hits: &HashMap<Branch, u64>) {
+        for (branch, branch_hits) in hits {
+            *self.campaign_hits.entry(*branch).or_insert(0) += *branch_hits;
+        }
+        self.given(fuzzer_id, hits);
+    }
+
+    // an input the fuzzer was given to use
+    pub fn given(&mut self, fuzzer_id: &str, hits: &HashMap<Branch, u64>) {
+        let known = self.known.entry(fuzzer_id.to_string()).or_default();
+        for (branch, branch_hits) in hits {
+            *known.entry(*branch).or_insert(0) |= hits_bucket(*branch_hits);
+        }
+    }
+
+    // the scores of an input for a fuzzer, computed as drivers do
+    pub fn scores(&self, fuzzer_id: &str, hits: &HashMap<Branch, u64>, input_size: u64,
+        metrics: &Metrics) -> RepMetric
+    {
+        let empty = HashMap::new();
+        let known = self.known.get(fuzzer_id).unwrap_or(&empty);
+        let new_branches = hits.keys().filter(|b| !known.contains_key(b)).count() as f64;
+
+        let scores = metrics.names().iter().map(|name| match name.as_str() {
+            "new-fraction" if !hits.is_empty() => new_branches / hits.len() as f64,
+            "new-fraction" => 0.0,
+            "hit-buckets" => hits.iter().filter(|t| {
+                known.get(t.0).map(|b| b & hits_bucket(*t.1) == 0).unwrap_or(true)
+            }).count() as f64,
+            "size-penalty" => new_branches / (1 + input_size / 1024) as f64,
+            // branches hit less in the whole campaign weigh more
+            "rarity" => hits.keys().filter(|b| !known.contains_key(b)).map(|b| {
+                1.0 / self.campaign_hits.get(b).cloned().unwrap_or(0).max(1) as f64
+            }).sum(),
+            _ => new_branches
+        }).collect();
+
+        RepMetric::new(scores, metrics.weights())
+    }
+}
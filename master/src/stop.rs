@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+
+use time::Duration;
+
+use config::{StopConfig, parse_duration};
+use fnv;
+
+
+// what the stop conditions are evaluated against
+pub struct CampaignProgress {
+    pub elapsed_time: Duration,
+    pub last_interesting: Duration,
+    pub last_coverage_increase: Duration,
+    pub unique_crashes: usize
+}
+
+
+pub struct StopConditions {
+    time_budget: Option<Duration>,
+    max_idle: Option<Duration>,
+    coverage_plateau: Option<Duration>,
+    target_crashes: Option<usize>
+}
+
+impl StopConditions {
+    pub fn from_config(config: &StopConfig) -> Result<StopConditions, String> {
+        let parse_opt = |s: &Option<String>| -> Result<Option<Duration>, String> {
+            match *s {
+                Some(ref s) => parse_duration(s).map(Some),
+                None => Ok(None)
+            }
+        };
+
+        Ok(StopConditions {
+            time_budget: parse_opt(&config.time_budget)?,
+            max_idle: parse_opt(&config.max_idle)?,
+            coverage_plateau: parse_opt(&config.coverage_plateau)?,
+            target_crashes: config.crashes
+        })
+    }
+
+    pub fn needs_crashes(&self) -> bool { self.target_crashes.is_some() }
+
+    // the reason to stop the campaign, if any condition is met
+    pub fn check(&self, progress: &CampaignProgress) -> Option<String> {
+        if let Some(budget) = self.time_budget {
+            if progress.elapsed_time >= budget {
+                return Some(format!("time budget of {}s exhausted", budget.num_seconds()));
+            }
+        }
+
+        if let Some(max_idle) = self.max_idle {
+            if progress.elapsed_time - progress.last_interesting >= max_idle {
+                return Some(format!("no interesting input for {}s", max_idle.num_seconds()));
+            }
+        }
+
+        if let Some(plateau) = self.coverage_plateau {
+            if progress.elapsed_time - progress.last_coverage_increase >= plateau {
+                return Some(format!("coverage did not increase for {}s", plateau.num_seconds()));
+            }
+        }
+
+        if let Some(target) = self.target_crashes {
+            if progress.unique_crashes >= target {
+                return Some(format!("found {} unique crashes", progress.unique_crashes));
+            }
+        }
+
+        None
+    }
+}
+
+
+// counts crashing inputs in the fuzzers' crash directories, deduplicated by content
+pub struct CrashCounter {
+    seen_paths: HashSet<PathBuf>,
+    hashes: HashSet<u64>
+}
+
+impl CrashCounter {
+    pub fn new() -> CrashCounter {
+        CrashCounter { seen_paths: HashSet::new(), hashes: HashSet::new() }
+    }
+
+    pub fn unique_crashes(&self) -> usize { self.hashes.len() }
+
+    // scan a crash directory, only files accepted by `is_crash` are considered
+    pub fn scan<F>(&mut self, crash_path: &str, is_crash: F) -> Result<(), String>
+        where F: Fn(&str) -> bool
+    {
+        let readdir = match fs::read_dir(crash_path) {
+            Ok(readdir) => readdir,
+            // fuzzers create their crash directory lazily
+            Err(_) => return Ok(())
+        };
+
+        for entry in readdir {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let is_new_crash = path.is_file() && !self.seen_paths.contains(&path) &&
+                path.file_name().map(|n| is_crash(&n.to_string_lossy())).unwrap_or(false);
+            if !is_new_crash {
+                continue;
+            }
+
+            let mut contents = vec![];
+            File::open(&path).and_then(|mut f| f.read_to_end(&mut contents)).map_err(|e| {
+                format!("failed to read crash {}: {}", path.display(), e)
+            })?;
+
+            self.hashes.insert(fnv::hash_bytes(&contents));
+            self.seen_paths.insert(path);
+        }
+
+        Ok(())
+    }
+}
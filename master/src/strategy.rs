@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+
+use time::Duration;
+
+use rand::{Rng, thread_rng};
+
+use common::LOG_LINE_SEPARATOR;
+use config::WinningConfig;
+use messages::{InterestingInput, RepMetric};
+
+
+// what a strategy gets to know about the campaign besides the metrics
+pub struct CampaignState<'a> {
+    pub interesting_input: &'a InterestingInput
+}
+
+
+pub trait WinningStrategy {
+    // choose which of the drivers in `metrics` should use the interesting input
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, state: &CampaignState)
+        -> Result<Vec<String>, String>;
+
+    // whether higher metric values are better, used to pick the best input for throttled fuzzers
+    fn is_high(&self) -> bool;
+
+    // a driver reported a new interesting input, with the signature of its coverage if known
+    fn interesting_reported(&mut self, _fuzzer_id: &str, _signature: Option<u64>,
+        _elapsed_time: Duration) {}
+
+    // an input (with the signature of its coverage if known) has been sent to the given drivers
+    fn inputs_assigned(&mut self, _fuzzer_ids: &[String], _signature: Option<u64>,
+        _elapsed_time: Duration) {}
+
+    // lines describing the internal state, dumped periodically to the strategy log
+    fn dump_state(&self) -> Vec<String> { vec![] }
+}
+
+
+pub fn from_config(config: &WinningConfig) -> Result<Box<dyn WinningStrategy>, String> {
+    let selected = [config.threshold.is_some(), config.top_k.is_some(),
+        config.proportional.is_some(), config.ucb_window.is_some()];
+    if selected.iter().filter(|s| **s).count() > 1 {
+        return Err("threshold, top-k, proportional and ucb strategies are exclusive".to_string());
+    }
+
+    let strategy: Box<dyn WinningStrategy> = if let Some(threshold) = config.threshold {
+        Box::new(MultipleWinners::new(threshold, config.high))
+    } else if let Some(k) = config.top_k {
+        Box::new(TopK::new(k, config.high))
+    } else if let Some(n) = config.proportional {
+        Box::new(Proportional::new(n, config.high))
+    } else if let Some(window) = config.ucb_window {
+        Box::new(Ucb1::new(Duration::milliseconds(window as i64), config.high))
+    } else {
+        Box::new(SingleWinner::new(config.high))
+    };
+
+    Ok(strategy)
+}
+
+
+// the driver with the highest (or lowest) metric wins
+pub struct SingleWinner {
+    highest: bool
+}
+
+impl SingleWinner {
+    pub fn new(highest: bool) -> SingleWinner {
+        SingleWinner { highest }
+    }
+}
+
+impl WinningStrategy for SingleWinner {
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, state: &CampaignState)
+        -> Result<Vec<String>, String>
+    {
+        if metrics.iter().all(|tpl| tpl.1.metric == 0.0) {
+            return Ok(vec![]);
+        }
+
+        // shuffling is done so that in cases where the metrics are all equal a different one gets
+        // picked each time
+        let mut metrics_vec: Vec<_> = metrics.iter().collect();
+        thread_rng().shuffle(metrics_vec.as_mut_slice());
+
+        let mut iter = metrics_vec.iter();
+        let (mut winning_key, mut winning_val) = *iter.next()
+            .ok_or(format!("no metrics for input from {}", state.interesting_input.fuzzer_id))?;
+
+        for tpl in iter {
+            let (k, v) = *tpl;
+            if (self.highest && v.metric > winning_val.metric) ||
+                (!self.highest && v.metric < winning_val.metric)
+            {
+                winning_val = v;
+                winning_key = k;
+            }
+        }
+
+        Ok(vec![winning_key.clone()])
+    }
+
+    fn is_high(&self) -> bool { self.highest }
+}
+
+
+// every driver with a metric higher (or lower) than the threshold wins
+pub struct MultipleWinners {
+    threshold: f64,
+    higher: bool
+}
+
+impl MultipleWinners {
+    pub fn new(threshold: f64, higher: bool) -> MultipleWinners {
+        MultipleWinners { threshold, higher }
+    }
+}
+
+impl WinningStrategy for MultipleWinners {
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, _: &CampaignState)
+        -> Result<Vec<String>, String>
+    {
+        let mut winners = vec![];
+
+        for (k, v) in metrics {
+            if (self.higher && v.metric > self.threshold) ||
+                (!self.higher && v.metric < self.threshold)
+            {
+                winners.push(k.clone());
+            }
+        }
+
+        Ok(winners)
+    }
+
+    fn is_high(&self) -> bool { self.higher }
+}
+
+
+// the k drivers with the highest (or lowest) metrics win
+pub struct TopK {
+    k: usize,
+    highest: bool
+}
+
+impl TopK {
+    pub fn new(k: usize, highest: bool) -> TopK {
+        TopK { k, highest }
+    }
+}
+
+impl WinningStrategy for TopK {
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, _: &CampaignState)
+        -> Result<Vec<String>, String>
+    {
+        if metrics.iter().all(|tpl| tpl.1.metric == 0.0) {
+            return Ok(vec![]);
+        }
+
+        // shuffle before the (stable) sort so that ties are broken randomly
+        let mut metrics_vec: Vec<_> = metrics.iter().collect();
+        thread_rng().shuffle(metrics_vec.as_mut_slice());
+        metrics_vec.sort_by(|t1, t2| {
+            let ord = t1.1.metric.partial_cmp(&t2.1.metric)
+                .unwrap_or(::std::cmp::Ordering::Equal);
+            if self.highest { ord.reverse() } else { ord }
+        });
+
+        Ok(metrics_vec.iter().take(self.k).map(|t| t.0.clone()).collect())
+    }
+
+    fn is_high(&self) -> bool { self.highest }
+}
+
+
+// n drivers are sampled (without replacement) with probability proportional to their metric, or
+// to its inverse when lower is better; drivers with a zero metric never win
+pub struct Proportional {
+    n: usize,
+    higher: bool
+}
+
+impl Proportional {
+    pub fn new(n: usize, higher: bool) -> Proportional {
+        Proportional { n, higher }
+    }
+}
+
+impl WinningStrategy for Proportional {
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, _: &CampaignState)
+        -> Result<Vec<String>, String>
+    {
+        let mut weighted: Vec<(&String, f64)> = metrics.iter().filter_map(|(k, v)| {
+            if v.metric <= 0.0 { None }
+            else if self.higher { Some((k, v.metric)) }
+            else { Some((k, 1.0 / v.metric)) }
+        }).collect();
+
+        let mut rng = thread_rng();
+        let mut winners = vec![];
+        while winners.len() < self.n && !weighted.is_empty() {
+            let total: f64 = weighted.iter().map(|t| t.1).sum();
+            let mut r = rng.gen_range(0.0, total);
+            let mut idx = weighted.len() - 1;
+            for (i, t) in weighted.iter().enumerate() {
+                if r < t.1 {
+                    idx = i;
+                    break;
+                }
+                r -= t.1;
+            }
+            winners.push(weighted.remove(idx).0.clone());
+        }
+
+        Ok(winners)
+    }
+
+    fn is_high(&self) -> bool { self.higher }
+}
+
+
+struct Arm {
+    pulls: u64,
+    rewards: u64,
+    // injection times and coverage signatures of the inputs not rewarded yet
+    pending: Vec<(Duration, Option<u64>)>
+}
+
+
+// UCB1 bandit where each receiving driver is an arm: the inputs sent to a driver are rewarded
+// (once) if the driver reports a new interesting input within `window` from their injection. The
+// winner is the arm with the highest upper confidence bound among the drivers with a non-zero
+// metric.
+pub struct Ucb1 {
+    window: Duration,
+    higher: bool,
+    arms: HashMap<String, Arm>,
+    total_pulls: u64
+}
+
+impl Ucb1 {
+    pub fn new(window: Duration, higher: bool) -> Ucb1 {
+        Ucb1 { window, higher, arms: HashMap::new(), total_pulls: 0 }
+    }
+
+    fn upper_bound(&self, fuzzer_id: &str) -> f64 {
+        match self.arms.get(fuzzer_id) {
+            Some(arm) if arm.pulls > 0 => {
+                let mean = arm.rewards as f64 / arm.pulls as f64;
+                let ln_total = (self.total_pulls as f64).ln();
+                mean + (2.0 * ln_total / arm.pulls as f64).sqrt()
+            },
+            _ => f64::INFINITY
+        }
+    }
+}
+
+impl WinningStrategy for Ucb1 {
+    fn winners(&mut self, metrics: &HashMap<String, RepMetric>, _: &CampaignState)
+        -> Result<Vec<String>, String>
+    {
+        // only drivers that would gain something from the input are candidates
+        let mut candidates: Vec<_> = metrics.iter().filter(|t| t.1.metric > 0.0)
+            .map(|t| t.0).collect();
+        thread_rng().shuffle(candidates.as_mut_slice());
+
+        let mut best: Option<(&String, f64)> = None;
+        for fuzzer_id in candidates {
+            let bound = self.upper_bound(fuzzer_id);
+            if best.map(|b| bound > b.1).unwrap_or(true) {
+                best = Some((fuzzer_id, bound));
+            }
+        }
+
+        Ok(best.map(|b| vec![b.0.clone()]).unwrap_or(vec![]))
+    }
+
+    fn is_high(&self) -> bool { self.higher }
+
+    fn interesting_reported(&mut self, fuzzer_id: &str, signature: Option<u64>,
+        elapsed_time: Duration)
+    {
+        if let Some(arm) = self.arms.get_mut(fuzzer_id) {
+            // fuzzers report the inputs they are given again once they import them, which tells
+            // nothing about whether they helped
+            if signature.is_some() && arm.pending.iter().any(|t| t.1 == signature) {
+                return;
+            }
+
+            let window = self.window;
+            if arm.pending.iter().any(|t| elapsed_time - t.0 <= window) {
+                arm.rewards += 1;
+            }
+            arm.pending.clear();
+        }
+    }
+
+    fn inputs_assigned(&mut self, fuzzer_ids: &[String], signature: Option<u64>,
+        elapsed_time: Duration)
+    {
+        let window = self.window;
+        for fuzzer_id in fuzzer_ids {
+            let arm = self.arms.entry(fuzzer_id.clone())
+                .or_insert(Arm { pulls: 0, rewards: 0, pending: vec![] });
+            arm.pending.retain(|t| elapsed_time - t.0 <= window);
+            arm.pending.push((elapsed_time, signature));
+            arm.pulls += 1;
+            self.total_pulls += 1;
+        }
+    }
+
+    fn dump_state(&self) -> Vec<String> {
+        let mut fuzzer_ids: Vec<_> = self.arms.keys().collect();
+        fuzzer_ids.sort();
+        fuzzer_ids.iter().map(|f| {
+            let arm = self.arms.get(*f).unwrap();
+            format!("{}{sep}{}{sep}{}{sep}{:.4}", f, arm.pulls, arm.rewards,
+                self.upper_bound(f), sep=LOG_LINE_SEPARATOR)
+        }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn metrics(values: &[(&str, f64)]) -> HashMap<String, RepMetric> {
+        values.iter().map(|t| (t.0.to_string(), RepMetric::new(vec![t.1], &[1.0]))).collect()
+    }
+
+    fn winners(strategy: &mut dyn WinningStrategy, metrics: &HashMap<String, RepMetric>)
+        -> Vec<String>
+    {
+        let input = InterestingInput {
+            fuzzer_id: "sender".to_string(),
+            input_path: "input".to_string(),
+            coverage_path: "coverage".to_string(),
+            data: None
+        };
+        let mut winners = strategy.winners(metrics, &CampaignState { interesting_input: &input })
+            .unwrap();
+        winners.sort();
+        winners
+    }
+
+    // the winners seen over enough rounds for every random choice to come up
+    fn all_winners(strategy: &mut dyn WinningStrategy, metrics: &HashMap<String, RepMetric>)
+        -> HashSet<String>
+    {
+        (0..200).flat_map(|_| winners(strategy, metrics)).collect()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn top_k_picks_the_best() {
+        let m = metrics(&[("a", 3.0), ("b", 1.0), ("c", 2.0)]);
+        assert_eq!(winners(&mut TopK::new(2, true), &m), ids(&["a", "c"]));
+        assert_eq!(winners(&mut TopK::new(2, false), &m), ids(&["b", "c"]));
+    }
+
+    #[test]
+    fn top_k_breaks_ties_randomly() {
+        let m = metrics(&[("a", 2.0), ("b", 2.0), ("c", 1.0)]);
+        let seen = all_winners(&mut TopK::new(1, true), &m);
+        assert_eq!(seen, ids(&["a", "b"]).into_iter().collect());
+
+        // a tie for the last place does not push out a better driver
+        let m = metrics(&[("a", 3.0), ("b", 2.0), ("c", 2.0)]);
+        let seen = all_winners(&mut TopK::new(2, true), &m);
+        assert_eq!(seen, ids(&["a", "b", "c"]).into_iter().collect());
+        for _ in 0..20 {
+            assert!(winners(&mut TopK::new(2, true), &m).contains(&"a".to_string()));
+        }
+    }
+
+    #[test]
+    fn top_k_larger_than_participants() {
+        let m = metrics(&[("a", 3.0), ("b", 1.0), ("c", 2.0)]);
+        assert_eq!(winners(&mut TopK::new(5, true), &m), ids(&["a", "b", "c"]));
+        assert!(winners(&mut TopK::new(5, true), &metrics(&[("a", 0.0), ("b", 0.0)])).is_empty());
+    }
+
+    #[test]
+    fn proportional_skips_zero_metrics() {
+        let m = metrics(&[("a", 0.0), ("b", 3.0), ("c", 0.0)]);
+        for higher in &[true, false] {
+            assert_eq!(all_winners(&mut Proportional::new(2, *higher), &m),
+                ids(&["b"]).into_iter().collect());
+        }
+        let m = metrics(&[("a", 0.0), ("b", 0.0)]);
+        assert!(winners(&mut Proportional::new(1, true), &m).is_empty());
+    }
+
+    #[test]
+    fn proportional_larger_than_participants() {
+        let m = metrics(&[("a", 1.0), ("b", 3.0)]);
+        assert_eq!(winners(&mut Proportional::new(3, true), &m), ids(&["a", "b"]));
+        // any driver with a metric can be sampled, even with a lower weight
+        assert_eq!(all_winners(&mut Proportional::new(1, true), &m),
+            ids(&["a", "b"]).into_iter().collect());
+    }
+
+    #[test]
+    fn ucb1_explores_arms_without_pulls() {
+        let mut ucb = Ucb1::new(Duration::seconds(60), true);
+        assert_eq!(ucb.upper_bound("a"), f64::INFINITY);
+
+        // arms never pulled have an unbounded estimate and win ties randomly
+        let m = metrics(&[("a", 1.0), ("b", 1.0)]);
+        assert_eq!(all_winners(&mut ucb, &m), ids(&["a", "b"]).into_iter().collect());
+
+        // once a has been pulled, b is explored first whatever the metrics
+        ucb.inputs_assigned(&ids(&["a"]), None, Duration::zero());
+        let m = metrics(&[("a", 10.0), ("b", 1.0)]);
+        assert_eq!(winners(&mut ucb, &m), ids(&["b"]));
+
+        // but not if it has nothing to gain from the input
+        let m = metrics(&[("a", 10.0), ("b", 0.0)]);
+        assert_eq!(winners(&mut ucb, &m), ids(&["a"]));
+    }
+
+    #[test]
+    fn ucb1_upper_bound_of_pulled_arms() {
+        let mut ucb = Ucb1::new(Duration::seconds(60), true);
+        ucb.inputs_assigned(&ids(&["a"]), Some(1), Duration::zero());
+        ucb.inputs_assigned(&ids(&["b"]), Some(2), Duration::seconds(1));
+
+        // a found something new within the window, b only reported the input it was given
+        ucb.interesting_reported("a", Some(3), Duration::seconds(10));
+        ucb.interesting_reported("b", Some(2), Duration::seconds(10));
+
+        let exploration = (2.0 * 2f64.ln()).sqrt();
+        assert!((ucb.upper_bound("a") - (1.0 + exploration)).abs() < 1e-9);
+        assert!((ucb.upper_bound("b") - exploration).abs() < 1e-9);
+        assert_eq!(winners(&mut ucb, &metrics(&[("a", 1.0), ("b", 1.0)])), ids(&["a"]));
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs;
+
+
+// how the master talks to the drivers it spawns: over tcp, as drivers running on other hosts
+// do, or over unix domain sockets living under the work directory
+
+const TCP_BIND_HOST: &str = "*";
+const TCP_CONN_HOST: &str = "localhost";
+const IPC_DIR: &str = "sockets";
+
+
+#[derive(Debug, Clone)]
+pub enum Transport {
+    Tcp,
+    Ipc(String)
+}
+
+impl Transport {
+    pub fn from_config(name: Option<&str>, work_path: &str) -> Result<Transport, String> {
+        match name.unwrap_or("tcp") {
+            "tcp" => Ok(Transport::Tcp),
+            "ipc" => {
+                // endpoints are passed to drivers separated by commas
+                if work_path.contains(',') {
+                    return Err(format!("ipc transport needs a work directory without commas, \
+                        not {}", work_path));
+                }
+
+                let path = format!("{}/{}", work_path, IPC_DIR);
+                fs::create_dir_all(&path).map_err(|e| {
+                    format!("failed to create socket directory {}: {}", path, e)
+                })?;
+                Ok(Transport::Ipc(path))
+            },
+            name => Err(format!("unknown transport {} (tcp or ipc)", name))
+        }
+    }
+
+    pub fn is_tcp(&self) -> bool {
+        match *self {
+            Transport::Tcp => true,
+            Transport::Ipc(_) => false
+        }
+    }
+
+    // endpoint to bind the socket called `name` to, the system picks a tcp port not given
+    pub fn bind_endpoint(&self, name: &str, port: Option<u32>) -> String {
+        match *self {
+            Transport::Tcp => match port {
+                Some(port) => format!("tcp://{}:{}", TCP_BIND_HOST, port),
+                None => format!("tcp://{}:*", TCP_BIND_HOST)
+            },
+            Transport::Ipc(ref path) => format!("ipc://{}/{}", path, name)
+        }
+    }
+
+    // endpoint to connect to from this host, given the one a socket ended up bound to
+    pub fn connect_endpoint(&self, bound_endpoint: &str) -> String {
+        match *self {
+            Transport::Tcp => {
+                let port = bound_endpoint.rsplit(':').next().unwrap_or("");
+                format!("tcp://{}:{}", TCP_CONN_HOST, port)
+            },
+            Transport::Ipc(_) => bound_endpoint.to_string()
+        }
+    }
+}
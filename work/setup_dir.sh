@@ -24,9 +24,9 @@ for conf_file in $conf_files; do
     rm -rf in driver *.log
 
     case $ftype in
-      afl|hongg)
+      afl|aflpp|hongg)
         rm -rf out
-        if [[ $ftype = afl ]]; then
+        if [[ $ftype = afl || $ftype = aflpp ]]; then
           inject_dir="out/inject/queue"
         else
           inject_dir="out/inject"
@@ -44,6 +44,12 @@ for conf_file in $conf_files; do
         # sudo mount -t tmpfs -o size=1024M tmpfs special
         echo A > image.offset
         ;;
+      libfuzzer)
+        # the corpus doubles as inject dir (run with -reload=1), crashes go where
+        # -artifact_prefix says, e.g. ./work/${folder}/crashes/
+        rm -rf corpus crashes
+        mkdir -p corpus crashes
+        ;;
       *)
         echo "Unrecognized fuzzer type for $conf_file: $ftype"
         popd > /dev/null